@@ -7,7 +7,7 @@ const CLIENT_CACHE_PATH: &str = "client.json";
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let credentials = read_credentials()?;
-    let mut tumblr_client = TumblrClient::try_from_file_or_authorize(
+    let tumblr_client = TumblrClient::try_from_file_or_authorize(
         CLIENT_CACHE_PATH.into(),
         credentials,
         reqwest::Client::new(),
@@ -1,42 +1,26 @@
 use tumblr_api::{
-    auth::read_credentials,
-    blog::TumblrBlogId,
-    post::{Formatting, PostContent, PostCreate, PostState},
-    requests::blog::posts::PostCreateRequest,
-    tags, TumblrClient,
+    auth::read_credentials, blog::TumblrBlogId, post::PostBuilder,
+    requests::blog::posts::PostCreateRequest, tags, TumblrClient,
 };
 
 const CLIENT_CACHE_PATH: &str = "client.json";
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let post = PostCreate {
-        content: vec![PostContent::Text {
-            text: "hello there".to_string(),
-            subtype: None,
-            indent_level: None,
-            formatting: Some(vec![Formatting::Color {
-                start: 0,
-                end: 11,
-                hex: "#ff0000".to_string(),
-            }]),
-        }],
-        state: Some(PostState::Draft),
-        tags: Some(
-            tags!(
-                "tumblr api",
-                "api",
-                "the pink hacker",
-                "tumblr api shenanigans"
-            )
-            .to_string(),
-        ),
-        ..Default::default()
-    };
+    let post = PostBuilder::new()
+        .paragraph(|p| p.color("hello there", "#ff0000"))
+        .tags(tags![
+            "tumblr api",
+            "api",
+            "the pink hacker",
+            "tumblr api shenanigans",
+        ]?)
+        .draft()
+        .build();
     println!("Post: {}", serde_json::to_string_pretty(&post)?);
 
     let credentials = read_credentials()?;
-    let mut tumblr_client = TumblrClient::try_from_file_or_authorize(
+    let tumblr_client = TumblrClient::try_from_file_or_authorize(
         CLIENT_CACHE_PATH.into(),
         credentials,
         reqwest::Client::new(),
@@ -50,6 +34,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .send_request(&PostCreateRequest {
             blog_id,
             parameters: post,
+            media: Vec::new(),
         })
         .await?;
     println!("Response: {:#?}", response);
@@ -0,0 +1,45 @@
+use tumblr_api::{
+    auth::read_credentials,
+    blog::TumblrBlogId,
+    post::{MediaObject, PostContent, PostCreate, PostState},
+    requests::{blog::posts::PostCreateRequest, media::MediaAttachment},
+    TumblrClient,
+};
+
+const CLIENT_CACHE_PATH: &str = "client.json";
+const IMAGE_IDENTIFIER: &str = "image-0";
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let post = PostCreate {
+        content: vec![PostContent::Image {
+            media: vec![MediaObject::from_identifier(IMAGE_IDENTIFIER)],
+            alt_text: Some("A local image.".to_string()),
+            caption: None,
+        }],
+        state: Some(PostState::Draft),
+        ..Default::default()
+    };
+
+    let credentials = read_credentials()?;
+    let tumblr_client = TumblrClient::try_from_file_or_authorize(
+        CLIENT_CACHE_PATH.into(),
+        credentials,
+        reqwest::Client::new(),
+    )
+    .await?;
+    tumblr_client.save_to_file(CLIENT_CACHE_PATH.into())?;
+
+    let blog_id = TumblrBlogId::BlogName("the-pink-hacker".to_string());
+
+    let response = tumblr_client
+        .send_request(&PostCreateRequest {
+            blog_id,
+            parameters: post,
+            media: vec![MediaAttachment::from_path(IMAGE_IDENTIFIER, "image.png")],
+        })
+        .await?;
+    println!("Response: {:#?}", response);
+    tumblr_client.save_to_file(CLIENT_CACHE_PATH.into())?;
+    Ok(())
+}
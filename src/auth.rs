@@ -2,28 +2,34 @@ use std::{
     fs::{self, File},
     io::{self, Write},
     path::PathBuf,
+    sync::{Arc, Mutex, MutexGuard, PoisonError, RwLock, RwLockReadGuard},
 };
 
 use chrono::{prelude::*, OutOfRangeError};
 use oauth2::{
     basic::{BasicClient, BasicErrorResponseType, BasicTokenType},
     reqwest::async_http_client,
-    AccessToken, AuthUrl, AuthorizationCode, Client, ClientId, ClientSecret, CsrfToken,
-    EmptyExtraTokenFields, PkceCodeChallenge, RedirectUrl, RefreshToken, RequestTokenError,
-    RevocationErrorResponseType, Scope, StandardErrorResponse, StandardRevocableToken,
-    StandardTokenIntrospectionResponse, StandardTokenResponse, TokenResponse, TokenUrl,
+    AccessToken, AuthUrl, AuthorizationCode, Client, ClientId, ClientSecret, EmptyExtraTokenFields,
+    RedirectUrl, RefreshToken, RequestTokenError, RevocationErrorResponseType,
+    StandardErrorResponse, StandardRevocableToken, StandardTokenIntrospectionResponse,
+    StandardTokenResponse, TokenResponse, TokenUrl,
 };
+use reqwest::Url;
 use serde::{Deserialize, Serialize};
 use snafu::prelude::*;
-use tokio::{
-    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
-    net::TcpListener,
+
+use crate::{
+    endpoints::Endpoints,
+    error::OAuthRequiredSnafu,
+    requests::rate_limit::{RateLimit, RetryPolicy},
+};
+#[cfg(feature = "testing")]
+use crate::{
+    error::CassetteWriteSnafu,
+    requests::cassette::{Cassette, CassetteMode},
 };
 
 const CREDENTIALS_PATH: &str = "credentials.json";
-const REDIRECT_URL: &str = "http://localhost:8080/";
-const AUTHORIZE_URL: &str = "https://www.tumblr.com/oauth2/authorize";
-const TOKEN_URL: &str = "https://api.tumblr.com/v2/oauth2/token";
 
 type TumblrTokenResponse = StandardTokenResponse<EmptyExtraTokenFields, BasicTokenType>;
 
@@ -36,6 +42,12 @@ type OauthClient = Client<
     StandardErrorResponse<RevocationErrorResponseType>,
 >;
 
+mod authorization;
+mod listener;
+mod store;
+
+pub use self::{authorization::PendingAuthorization, listener::RedirectListener, store::*};
+
 type Result<T> = core::result::Result<T, Error>;
 
 #[derive(Debug, Snafu)]
@@ -69,6 +81,25 @@ pub enum Error {
     DeserializeClientCacheFile { source: serde_json::Error },
     #[snafu(display("Failed to serialize client cache file: {}", source))]
     SerializeClientCacheFile { source: serde_json::Error },
+    #[snafu(display("Authorization was denied: {}", error))]
+    AuthorizationDenied {
+        /// Such as `access_denied` when the user declined.
+        error: String,
+        description: Option<String>,
+    },
+    #[snafu(display("The redirect has no CSRF state to check."))]
+    MissingState,
+    #[snafu(display("Timed out waiting for authorization."))]
+    AuthorizationTimeout,
+    #[snafu(display("The client only has an API key, it isn't authorized with OAuth."))]
+    NotAuthorized,
+    #[snafu(display("No tokens were stored."))]
+    NoStoredTokens,
+    /// For [`TokenStore`] implementations whose errors don't fit the others.
+    #[snafu(display("Token store failed: {}", source))]
+    TokenStore {
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
 }
 
 #[derive(Deserialize)]
@@ -82,121 +113,21 @@ pub fn read_credentials() -> io::Result<ConsumerCredentials> {
     Ok(serde_json::from_str(&file_contents)?)
 }
 
-async fn listen_for_code() -> Result<(AuthorizationCode, CsrfToken)> {
-    let listener = TcpListener::bind("127.0.0.1:8080")
-        .await
-        .context(RedirectServerSnafu)?;
-    loop {
-        if let Ok((stream, _)) = listener.accept().await {
-            let code;
-            let state;
-            let mut buffer;
-            {
-                buffer = BufReader::new(stream);
-
-                let mut request_line = String::new();
-                buffer
-                    .read_line(&mut request_line)
-                    .await
-                    .context(RedirectServerSnafu)?;
-
-                let redirect_url = serde_urlencoded::from_str::<RedirectUrlArguments>(
-                    request_line
-                        .split_whitespace()
-                        .nth(1)
-                        .context(UrlParseSnafu)?
-                        .split('?')
-                        .nth(1)
-                        .context(UrlParseSnafu)?,
-                )
-                .context(UrlArgumentsParseSnafu)?;
-
-                code = AuthorizationCode::new(redirect_url.code);
-                state = CsrfToken::new(redirect_url.state);
-            }
-
-            let message = "Go back to your terminal :)";
-            let response = format!(
-                "HTTP/1.1 200 OK\r\ncontent-length: {}\r\n\r\n{}",
-                message.len(),
-                message
-            );
-
-            buffer
-                .write_all(response.as_bytes())
-                .await
-                .context(RedirectServerSnafu)?;
-
-            return Ok((code, state));
-        }
-    }
-}
-
-#[derive(Debug, Deserialize)]
-struct RedirectUrlArguments {
-    pub code: String,
-    pub state: String,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-struct TumblrClientTokens {
-    access_token: AccessToken,
-    refresh_at: DateTime<Utc>,
-    refresh_token: Option<RefreshToken>,
+/// The tokens a [`TumblrClient`] is authorized with, as saved by a [`TokenStore`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TumblrClientTokens {
+    pub access_token: AccessToken,
+    /// When the access token should be refreshed.
+    pub refresh_at: DateTime<Utc>,
+    pub refresh_token: Option<RefreshToken>,
 }
 
 impl TumblrClientTokens {
-    async fn authorize(client: &OauthClient) -> Result<Self> {
-        let (pkce_challenge, pkce_verifier) = PkceCodeChallenge::new_random_sha256();
-
-        let (auth_url, csrf_token) = client
-            .authorize_url(CsrfToken::new_random)
-            .add_scopes([
-                Scope::new("offline_access".to_string()),
-                Scope::new("basic".to_string()),
-                Scope::new("write".to_string()),
-            ])
-            .set_pkce_challenge(pkce_challenge)
-            .url();
-
-        println!("Browse to: {}", auth_url);
-
-        // Redirect server
-        let (code, state) = listen_for_code().await?;
-
-        // The state received from Tumblr, should match what we sent
-        ensure!(
-            csrf_token.secret() == state.secret(),
-            StateDoNotMatchSnafu {
-                expected: csrf_token.secret(),
-                result: state.secret()
-            }
-        );
-
-        // Exchange the code with a token.
-        client
-            .exchange_code(code)
-            .set_pkce_verifier(pkce_verifier)
-            .request_async(async_http_client)
-            .await
-            .context(AccessTokenSnafu)?
-            .try_into()
-    }
-
     fn from_file(path: PathBuf) -> Result<Self> {
         let contents = fs::read_to_string(path).context(LoadClientCacheFileSnafu)?;
         serde_json::from_str(&contents).context(DeserializeClientCacheFileSnafu)
     }
 
-    async fn try_from_file_or_authorize(path: PathBuf, client: &OauthClient) -> Result<Self> {
-        if let Ok(cached) = Self::from_file(path) {
-            println!("Client cached. Loading from file.");
-            Ok(cached)
-        } else {
-            Self::authorize(client).await
-        }
-    }
-
     fn save_to_file(&self, path: PathBuf) -> Result<()> {
         let mut file = File::create(path).context(SaveClientCacheFileSnafu)?;
         let contents = serde_json::to_string_pretty(self).context(SerializeClientCacheFileSnafu)?;
@@ -204,19 +135,6 @@ impl TumblrClientTokens {
             .context(SaveClientCacheFileSnafu)
     }
 
-    async fn refresh_token(&mut self, client: &OauthClient) -> Result<()> {
-        let refresh_token = self
-            .refresh_token
-            .as_ref()
-            .expect("Refresh token no present.");
-        let response = client
-            .exchange_refresh_token(&refresh_token)
-            .request_async(async_http_client)
-            .await
-            .context(AccessTokenSnafu)?;
-        self.load_response(response)
-    }
-
     /// Takes `expires_in` and adds it to the current time.
     fn calculate_refresh_at(expires_in: Option<std::time::Duration>) -> Result<DateTime<Utc>> {
         let expires_in = chrono::Duration::from_std(expires_in.context(NoRefreshTokenSnafu)?)
@@ -228,15 +146,6 @@ impl TumblrClientTokens {
         self.refresh_at <= Utc::now()
     }
 
-    async fn refresh_token_if_expired(&mut self, client: &OauthClient) -> Result<()> {
-        if self.needs_refresh() {
-            println!("Refreshing token.");
-            self.refresh_token(client).await
-        } else {
-            Ok(())
-        }
-    }
-
     fn load_response(&mut self, response: TumblrTokenResponse) -> Result<()> {
         self.access_token = response.access_token().clone();
         self.refresh_at = Self::calculate_refresh_at(response.expires_in())?;
@@ -257,24 +166,100 @@ impl TryFrom<TumblrTokenResponse> for TumblrClientTokens {
     }
 }
 
-#[derive(Debug)]
+/// A client for the Tumblr API.
+///
+/// Clones share their tokens, rate limits and cassette, so one client can be cloned into as
+/// many tasks as needed. When the access token expires, the first request to notice refreshes
+/// it while the others wait and then use the new one.
+///
+/// ```no_run
+/// # use tumblr_api::{
+/// #     auth::read_credentials, blog::TumblrBlogId, requests::blog::posts::PostGetRequest,
+/// #     TumblrClient,
+/// # };
+/// # async fn fetch() -> Result<(), Box<dyn std::error::Error>> {
+/// let client = TumblrClient::builder(read_credentials()?).from_file("client.json".into())?;
+/// let tasks: Vec<_> = ["1", "2", "3"]
+///     .into_iter()
+///     .map(|post_id| {
+///         let client = client.clone();
+///         tokio::spawn(async move {
+///             client
+///                 .send_request(&PostGetRequest {
+///                     blog_id: TumblrBlogId::BlogName("staff".to_string()),
+///                     post_id: post_id.to_string(),
+///                 })
+///                 .await
+///         })
+///     })
+///     .collect();
+/// for task in tasks {
+///     task.await??;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
 pub struct TumblrClient {
-    token: TumblrClientTokens,
+    /// Only locked briefly to read or replace the tokens, never across an await. `None` for
+    /// clients that only have an API key.
+    token: Arc<RwLock<Option<TumblrClientTokens>>>,
+    /// Held while refreshing, so only one refresh is in flight.
+    refreshing: Arc<tokio::sync::Mutex<()>>,
     pub client: OauthClient,
     pub request_client: reqwest::Client,
+    pub retry_policy: RetryPolicy,
+    rate_limit: Arc<Mutex<Option<RateLimit>>>,
+    endpoints: Endpoints,
+    token_store: Option<Arc<dyn TokenStore>>,
+    #[cfg(feature = "testing")]
+    cassette: Arc<Mutex<Option<Cassette>>>,
 }
 
 impl TumblrClient {
+    pub fn builder(credentials: ConsumerCredentials) -> TumblrClientBuilder {
+        TumblrClientBuilder::new(credentials)
+    }
+
+    /// A client for requests that only need the consumer key, such as
+    /// [`BlogInfoRequest`](crate::requests::blog::blog::BlogInfoRequest). Requests that need
+    /// OAuth fail with [`Error::OAuthRequired`](crate::Error::OAuthRequired) before being sent.
+    ///
+    /// ```no_run
+    /// # use tumblr_api::{
+    /// #     blog::TumblrBlogId, requests::blog::blog::BlogInfoRequest, TumblrClient,
+    /// # };
+    /// # async fn crawl() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = TumblrClient::from_api_key("consumer key");
+    /// let blog = client
+    ///     .send_request(&BlogInfoRequest {
+    ///         blog_id: TumblrBlogId::BlogName("staff".to_string()),
+    ///     })
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_api_key(consumer_key: impl Into<String>) -> Self {
+        Self::builder(ConsumerCredentials {
+            consumer_key: consumer_key.into(),
+            consumer_secret: String::new(),
+        })
+        .api_key_only()
+    }
+
+    /// Whether the client has OAuth tokens, rather than only an API key.
+    pub fn is_authorized(&self) -> bool {
+        self.tokens().is_some()
+    }
+
     pub async fn authorize(
         credentials: ConsumerCredentials,
         request_client: reqwest::Client,
     ) -> Result<Self> {
-        let client = Self::create_oauth_client(credentials);
-        Ok(Self {
-            token: TumblrClientTokens::authorize(&client).await?,
-            client,
-            request_client,
-        })
+        Self::builder(credentials)
+            .request_client(request_client)
+            .authorize()
+            .await
     }
 
     pub fn from_file(
@@ -282,11 +267,9 @@ impl TumblrClient {
         credentials: ConsumerCredentials,
         request_client: reqwest::Client,
     ) -> Result<Self> {
-        Ok(Self {
-            token: TumblrClientTokens::from_file(path)?,
-            client: Self::create_oauth_client(credentials),
-            request_client,
-        })
+        Self::builder(credentials)
+            .request_client(request_client)
+            .from_file(path)
     }
 
     pub async fn try_from_file_or_authorize(
@@ -294,43 +277,463 @@ impl TumblrClient {
         credentials: ConsumerCredentials,
         request_client: reqwest::Client,
     ) -> Result<Self> {
-        let client = Self::create_oauth_client(credentials);
-        Ok(Self {
-            token: TumblrClientTokens::try_from_file_or_authorize(path, &client).await?,
-            client,
-            request_client,
-        })
+        Self::builder(credentials)
+            .request_client(request_client)
+            .try_from_file_or_authorize(path)
+            .await
     }
 
     pub fn save_to_file(&self, path: PathBuf) -> Result<()> {
-        self.token.save_to_file(path)
+        self.tokens()
+            .as_ref()
+            .context(NotAuthorizedSnafu)?
+            .save_to_file(path)
     }
 
-    pub async fn refresh_token(&mut self) -> Result<()> {
-        self.token.refresh_token(&self.client).await
+    pub async fn refresh_token(&self) -> Result<()> {
+        let _refreshing = self.refreshing.lock().await;
+        self.exchange_refresh_token().await
     }
 
-    pub async fn refresh_token_if_expired(&mut self) -> Result<()> {
-        self.token.refresh_token_if_expired(&self.client).await
+    pub async fn refresh_token_if_expired(&self) -> Result<()> {
+        if !self.needs_refresh() {
+            return Ok(());
+        }
+
+        let _refreshing = self.refreshing.lock().await;
+        // Another task may have refreshed it while we waited.
+        if self.needs_refresh() {
+            self.exchange_refresh_token().await
+        } else {
+            Ok(())
+        }
+    }
+
+    async fn exchange_refresh_token(&self) -> Result<()> {
+        let refresh_token = self
+            .tokens()
+            .as_ref()
+            .context(NotAuthorizedSnafu)?
+            .refresh_token
+            .clone()
+            .context(NoRefreshTokenSnafu)?;
+        let response = self
+            .client
+            .exchange_refresh_token(&refresh_token)
+            .request_async(async_http_client)
+            .await
+            .context(AccessTokenSnafu)?;
+        // The store may do I/O, so it's given a copy once the lock is released.
+        let token = {
+            let mut token = self.token.write().unwrap_or_else(PoisonError::into_inner);
+            let token = token.as_mut().context(NotAuthorizedSnafu)?;
+            token.load_response(response)?;
+            token.clone()
+        };
+        match &self.token_store {
+            Some(store) => store.save(&token),
+            None => Ok(()),
+        }
+    }
+
+    /// API key only clients never need to.
+    fn needs_refresh(&self) -> bool {
+        self.tokens()
+            .as_ref()
+            .is_some_and(TumblrClientTokens::needs_refresh)
+    }
+
+    fn tokens(&self) -> RwLockReadGuard<'_, Option<TumblrClientTokens>> {
+        self.token.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Records requests to, or replays them from, `cassette`.
+    #[cfg(feature = "testing")]
+    pub fn with_cassette(self, cassette: Cassette) -> Self {
+        *self.cassette() = Some(cassette);
+        self
+    }
+
+    /// Takes the attached cassette back, e.g. to inspect what was recorded.
+    #[cfg(feature = "testing")]
+    pub fn take_cassette(&self) -> Option<Cassette> {
+        self.cassette().take()
+    }
+
+    /// Writes out what the attached cassette has recorded so far.
+    #[cfg(feature = "testing")]
+    pub fn flush_cassette(&self) -> crate::error::Result<()> {
+        match self.cassette().as_mut() {
+            Some(cassette) => cassette.flush().context(CassetteWriteSnafu),
+            None => Ok(()),
+        }
+    }
+
+    #[cfg(feature = "testing")]
+    pub(crate) fn cassette(&self) -> MutexGuard<'_, Option<Cassette>> {
+        self.cassette.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    #[cfg(feature = "testing")]
+    pub(crate) fn cassette_mode(&self) -> Option<CassetteMode> {
+        self.cassette().as_ref().map(Cassette::mode)
+    }
+
+    /// The most recent rate limits Tumblr reported.
+    pub fn rate_limit(&self) -> Option<RateLimit> {
+        self.rate_limit_guard().clone()
+    }
+
+    pub(crate) fn set_rate_limit(&self, rate_limit: RateLimit) {
+        *self.rate_limit_guard() = Some(rate_limit);
+    }
+
+    fn rate_limit_guard(&self) -> MutexGuard<'_, Option<RateLimit>> {
+        self.rate_limit
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn endpoints(&self) -> &Endpoints {
+        &self.endpoints
+    }
+
+    /// The prefix API paths are joined onto.
+    pub fn api_base(&self) -> &Url {
+        &self.endpoints.api_base
     }
 
     pub fn get_api_key(&self) -> &ClientId {
         self.client.client_id()
     }
 
-    pub fn get_access_token(&self) -> &AccessToken {
-        &self.token.access_token
+    /// Fails for clients that only have an API key.
+    pub fn get_access_token(&self) -> crate::error::Result<AccessToken> {
+        Ok(self
+            .tokens()
+            .as_ref()
+            .context(OAuthRequiredSnafu)?
+            .access_token
+            .clone())
+    }
+}
+
+/// Configures a [`TumblrClient`] before it's authorized.
+///
+/// ```no_run
+/// # use tumblr_api::{auth::read_credentials, endpoints::Endpoints, TumblrClient};
+/// # async fn connect() -> Result<(), Box<dyn std::error::Error>> {
+/// let endpoints = Endpoints::local(&"http://127.0.0.1:3000/".parse()?)?;
+/// let client = TumblrClient::builder(read_credentials()?)
+///     .endpoints(endpoints)
+///     .from_file("client.json".into())?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct TumblrClientBuilder {
+    credentials: ConsumerCredentials,
+    request_client: reqwest::Client,
+    endpoints: Endpoints,
+    retry_policy: RetryPolicy,
+    token_store: Option<Arc<dyn TokenStore>>,
+    scopes: Vec<String>,
+    redirect_listener: RedirectListener,
+    #[cfg(feature = "testing")]
+    cassette: Option<Cassette>,
+}
+
+impl TumblrClientBuilder {
+    pub fn new(credentials: ConsumerCredentials) -> Self {
+        Self {
+            credentials,
+            request_client: reqwest::Client::new(),
+            endpoints: Endpoints::default(),
+            retry_policy: RetryPolicy::default(),
+            token_store: None,
+            scopes: authorization::DEFAULT_SCOPES
+                .map(ToString::to_string)
+                .to_vec(),
+            redirect_listener: RedirectListener::default(),
+            #[cfg(feature = "testing")]
+            cassette: None,
+        }
+    }
+
+    pub fn request_client(mut self, request_client: reqwest::Client) -> Self {
+        self.request_client = request_client;
+        self
+    }
+
+    pub fn endpoints(mut self, endpoints: Endpoints) -> Self {
+        self.endpoints = endpoints;
+        self
+    }
+
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// The scopes to ask the user for, `offline_access basic write` by default. Without
+    /// `offline_access` Tumblr doesn't give a refresh token.
+    ///
+    /// https://www.tumblr.com/docs/en/api/v2#oauth2authorize---authorization-request
+    pub fn scopes<S: Into<String>>(mut self, scopes: impl IntoIterator<Item = S>) -> Self {
+        self.scopes = scopes.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn redirect_listener(mut self, redirect_listener: RedirectListener) -> Self {
+        self.redirect_listener = redirect_listener;
+        self
+    }
+
+    /// Saves tokens to `token_store` after authorizing and after every refresh.
+    pub fn token_store(mut self, token_store: impl TokenStore + 'static) -> Self {
+        self.token_store = Some(Arc::new(token_store));
+        self
+    }
+
+    #[cfg(feature = "testing")]
+    pub fn cassette(mut self, cassette: Cassette) -> Self {
+        self.cassette = Some(cassette);
+        self
+    }
+
+    /// Sends the user through Tumblr's authorization page, waiting for them on the
+    /// [`redirect_listener`](Self::redirect_listener).
+    pub async fn authorize(self) -> Result<TumblrClient> {
+        let client = self.create_oauth_client();
+        let pending = PendingAuthorization::new(&client, &self.scopes);
+        println!("Browse to: {}", pending.url);
+
+        let (code, state) = self
+            .redirect_listener
+            .listen(&self.endpoints.redirect)
+            .await?;
+        let token = pending.exchange(&client, code, Some(state)).await?;
+        self.finish_authorized(client, token)
+    }
+
+    /// Starts authorizing without a redirect listener, see [`PendingAuthorization`].
+    pub fn begin_authorization(&self) -> PendingAuthorization {
+        PendingAuthorization::new(&self.create_oauth_client(), &self.scopes)
     }
 
-    fn create_oauth_client(credentials: ConsumerCredentials) -> OauthClient {
+    /// Finishes an authorization from [`begin_authorization`](Self::begin_authorization).
+    ///
+    /// `redirect` is the URL Tumblr redirected the user to, or just its query. Its CSRF state
+    /// has to match the pending authorization's.
+    pub async fn complete_authorization(
+        self,
+        pending: PendingAuthorization,
+        redirect: &str,
+    ) -> Result<TumblrClient> {
+        let (code, state) = authorization::parse_redirect(redirect)?;
+        let client = self.create_oauth_client();
+        let token = pending.exchange(&client, code, Some(state)).await?;
+        self.finish_authorized(client, token)
+    }
+
+    /// Like [`complete_authorization`](Self::complete_authorization) with only the code, which
+    /// skips the CSRF check. Only use it when the code can't have come from anyone but the user.
+    pub async fn complete_authorization_with_code(
+        self,
+        pending: PendingAuthorization,
+        code: &str,
+    ) -> Result<TumblrClient> {
+        let code = AuthorizationCode::new(code.trim().to_string());
+        let client = self.create_oauth_client();
+        let token = pending.exchange(&client, code, None).await?;
+        self.finish_authorized(client, token)
+    }
+
+    /// Skips OAuth, for requests that only need the consumer key. See
+    /// [`TumblrClient::from_api_key`].
+    pub fn api_key_only(self) -> TumblrClient {
+        let client = self.create_oauth_client();
+        self.build(client, None)
+    }
+
+    /// Uses the tokens in the [`token_store`](Self::token_store).
+    pub fn from_store(self) -> Result<TumblrClient> {
+        let token = self
+            .token_store
+            .as_ref()
+            .context(NoStoredTokensSnafu)?
+            .load()?
+            .context(NoStoredTokensSnafu)?;
+        let client = self.create_oauth_client();
+        Ok(self.finish(client, token))
+    }
+
+    /// Uses the tokens in the [`token_store`](Self::token_store), authorizing if it's empty.
+    pub async fn load_or_authorize(self) -> Result<TumblrClient> {
+        let stored = match &self.token_store {
+            Some(store) => store.load()?,
+            None => None,
+        };
+        match stored {
+            Some(token) => {
+                let client = self.create_oauth_client();
+                Ok(self.finish(client, token))
+            }
+            None => self.authorize().await,
+        }
+    }
+
+    /// Loads tokens saved by [`TumblrClient::save_to_file`].
+    pub fn from_file(self, path: PathBuf) -> Result<TumblrClient> {
+        let token = TumblrClientTokens::from_file(path)?;
+        let client = self.create_oauth_client();
+        Ok(self.finish(client, token))
+    }
+
+    /// Uses tokens obtained elsewhere, `refresh_at` is when the access token should be
+    /// refreshed.
+    pub fn from_tokens(
+        self,
+        access_token: AccessToken,
+        refresh_token: Option<RefreshToken>,
+        refresh_at: DateTime<Utc>,
+    ) -> TumblrClient {
+        let client = self.create_oauth_client();
+        let token = TumblrClientTokens {
+            access_token,
+            refresh_at,
+            refresh_token,
+        };
+        self.finish(client, token)
+    }
+
+    pub async fn try_from_file_or_authorize(self, path: PathBuf) -> Result<TumblrClient> {
+        match TumblrClientTokens::from_file(path) {
+            Ok(token) => {
+                println!("Client cached. Loading from file.");
+                let client = self.create_oauth_client();
+                Ok(self.finish(client, token))
+            }
+            Err(_) => self.authorize().await,
+        }
+    }
+
+    fn finish_authorized(
+        self,
+        client: OauthClient,
+        token: TumblrClientTokens,
+    ) -> Result<TumblrClient> {
+        if let Some(store) = &self.token_store {
+            store.save(&token)?;
+        }
+        Ok(self.finish(client, token))
+    }
+
+    fn finish(self, client: OauthClient, token: TumblrClientTokens) -> TumblrClient {
+        self.build(client, Some(token))
+    }
+
+    fn build(self, client: OauthClient, token: Option<TumblrClientTokens>) -> TumblrClient {
+        TumblrClient {
+            token: Arc::new(RwLock::new(token)),
+            refreshing: Arc::default(),
+            client,
+            request_client: self.request_client,
+            retry_policy: self.retry_policy,
+            rate_limit: Arc::default(),
+            endpoints: self.endpoints,
+            token_store: self.token_store,
+            #[cfg(feature = "testing")]
+            cassette: Arc::new(Mutex::new(self.cassette)),
+        }
+    }
+
+    fn create_oauth_client(&self) -> OauthClient {
         BasicClient::new(
-            ClientId::new(credentials.consumer_key),
-            Some(ClientSecret::new(credentials.consumer_secret)),
-            AuthUrl::new(AUTHORIZE_URL.to_string()).expect("Auth URL failed to be created."),
-            Some(TokenUrl::new(TOKEN_URL.to_string()).expect("Token URL failed to be created.")),
-        )
-        .set_redirect_uri(
-            RedirectUrl::new(REDIRECT_URL.to_string()).expect("Redirect URL failed to be created."),
+            ClientId::new(self.credentials.consumer_key.clone()),
+            Some(ClientSecret::new(self.credentials.consumer_secret.clone())),
+            AuthUrl::from_url(self.endpoints.authorize.clone()),
+            Some(TokenUrl::from_url(self.endpoints.token.clone())),
         )
+        .set_redirect_uri(RedirectUrl::from_url(self.endpoints.redirect.clone()))
+    }
+}
+
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+    use chrono::Utc;
+
+    use crate::{
+        blog::TumblrBlogId, post::PostBuilder, requests::blog::posts::PostSearchRequest,
+        testing::MockTumblr, Error, TumblrClient,
+    };
+
+    #[tokio::test]
+    async fn concurrent_refreshes_hit_the_token_endpoint_once() {
+        let mock = MockTumblr::start().await.unwrap();
+        let client = mock.client();
+        let old_token = client
+            .tokens()
+            .as_ref()
+            .unwrap()
+            .access_token
+            .secret()
+            .clone();
+        client.token.write().unwrap().as_mut().unwrap().refresh_at = Utc::now();
+
+        let refreshes = (0..8)
+            .map(|_| {
+                let client = client.clone();
+                tokio::spawn(async move { client.refresh_token_if_expired().await })
+            })
+            .collect::<Vec<_>>();
+        for refresh in refreshes {
+            refresh.await.unwrap().unwrap();
+        }
+
+        let token_requests = mock
+            .requests()
+            .into_iter()
+            .filter(|request| request.path.ends_with("oauth2/token"))
+            .count();
+        assert_eq!(token_requests, 1);
+        assert_ne!(
+            client.tokens().as_ref().unwrap().access_token.secret(),
+            &old_token
+        );
+    }
+
+    #[tokio::test]
+    async fn api_key_clients_read_but_cant_write() {
+        let mock = MockTumblr::start().await.unwrap();
+        mock.add_blog("staff");
+        mock.add_post(
+            "staff",
+            serde_json::json!({ "content": [{ "type": "text", "text": "Hello!" }] }),
+        );
+        let client = TumblrClient::builder(mock.credentials())
+            .endpoints(mock.endpoints())
+            .api_key_only();
+        let staff = TumblrBlogId::BlogName("staff".to_string());
+
+        let posts = client
+            .send_request(&PostSearchRequest::new(staff.clone()))
+            .await
+            .unwrap()
+            .response
+            .posts;
+        assert_eq!(posts.len(), 1);
+        assert_eq!(posts[0].to_plain_text(), "Hello!");
+
+        let request = PostBuilder::new()
+            .paragraph(|p| p.text("Hi!"))
+            .build_request(staff);
+        let error = client.send_request(&request).await.unwrap_err();
+        assert!(matches!(error, Error::OAuthRequired), "{error:?}");
+        assert_eq!(mock.requests().len(), 1);
     }
 }
@@ -0,0 +1,194 @@
+use oauth2::{
+    reqwest::async_http_client, AuthorizationCode, CsrfToken, PkceCodeChallenge, PkceCodeVerifier,
+    Scope,
+};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use snafu::prelude::*;
+
+use super::{
+    AccessTokenSnafu, AuthorizationDeniedSnafu, MissingStateSnafu, OauthClient, Result,
+    StateDoNotMatchSnafu, TumblrClientTokens, UrlArgumentsParseSnafu, UrlParseSnafu,
+};
+
+/// `offline_access` is what gets a refresh token.
+pub(crate) const DEFAULT_SCOPES: [&str; 3] = ["offline_access", "basic", "write"];
+
+/// An authorization waiting for the user to approve it on Tumblr, for servers and other places
+/// a redirect listener can't run.
+///
+/// Send the user to `url`, then pass what Tumblr redirected them back with to
+/// [`TumblrClientBuilder::complete_authorization`](super::TumblrClientBuilder::complete_authorization).
+/// It can be serialized to keep it around between the two, it holds secrets so keep it private.
+///
+/// ```no_run
+/// # use tumblr_api::{auth::read_credentials, TumblrClient};
+/// # async fn connect(pasted: &str) -> Result<(), Box<dyn std::error::Error>> {
+/// let builder = TumblrClient::builder(read_credentials()?);
+/// let pending = builder.begin_authorization();
+/// // Show `pending.url` to the user, who pastes back the URL they were redirected to.
+/// let client = builder.complete_authorization(pending, pasted).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PendingAuthorization {
+    /// Tumblr's authorization page for the user.
+    pub url: Url,
+    /// Tumblr sends this back with the code, it must match.
+    pub csrf_state: CsrfToken,
+    pub pkce_verifier: PkceCodeVerifier,
+}
+
+impl PendingAuthorization {
+    pub(crate) fn new(client: &OauthClient, scopes: &[String]) -> Self {
+        let (pkce_challenge, pkce_verifier) = PkceCodeChallenge::new_random_sha256();
+        let (url, csrf_state) = client
+            .authorize_url(CsrfToken::new_random)
+            .add_scopes(scopes.iter().cloned().map(Scope::new))
+            .set_pkce_challenge(pkce_challenge)
+            .url();
+        Self {
+            url,
+            csrf_state,
+            pkce_verifier,
+        }
+    }
+
+    /// Exchanges the code for tokens, checking `state` against ours when there is one.
+    pub(crate) async fn exchange(
+        self,
+        client: &OauthClient,
+        code: AuthorizationCode,
+        state: Option<CsrfToken>,
+    ) -> Result<TumblrClientTokens> {
+        if let Some(state) = state {
+            ensure!(
+                self.csrf_state.secret() == state.secret(),
+                StateDoNotMatchSnafu {
+                    expected: self.csrf_state.secret(),
+                    result: state.secret()
+                }
+            );
+        }
+
+        client
+            .exchange_code(code)
+            .set_pkce_verifier(self.pkce_verifier)
+            .request_async(async_http_client)
+            .await
+            .context(AccessTokenSnafu)?
+            .try_into()
+    }
+}
+
+/// What Tumblr adds to the redirect URL, either a code or an error.
+#[derive(Debug, Deserialize)]
+struct RedirectArguments {
+    code: Option<String>,
+    state: Option<String>,
+    error: Option<String>,
+    error_description: Option<String>,
+}
+
+/// Reads the code from a redirect's query, `None` if it has neither a code nor an error.
+pub(crate) fn parse_query(query: &str) -> Result<Option<(AuthorizationCode, CsrfToken)>> {
+    let arguments =
+        serde_urlencoded::from_str::<RedirectArguments>(query).context(UrlArgumentsParseSnafu)?;
+    if let Some(error) = arguments.error {
+        return AuthorizationDeniedSnafu {
+            error,
+            description: arguments.error_description,
+        }
+        .fail();
+    }
+    Ok(arguments.code.map(|code| {
+        (
+            AuthorizationCode::new(code),
+            CsrfToken::new(arguments.state.unwrap_or_default()),
+        )
+    }))
+}
+
+/// Reads the code and state from a redirect URL or its query.
+///
+/// A bare code has no state to check, so it's refused here, see
+/// [`TumblrClientBuilder::complete_authorization_with_code`](super::TumblrClientBuilder::complete_authorization_with_code).
+pub(crate) fn parse_redirect(input: &str) -> Result<(AuthorizationCode, CsrfToken)> {
+    let input = input.trim();
+    ensure!(
+        input.contains("code=") || input.contains("error="),
+        MissingStateSnafu
+    );
+
+    let query = input.split_once('?').map_or(input, |(_, query)| query);
+    let query = query.split_once('#').map_or(query, |(query, _)| query);
+    parse_query(query)?.context(UrlParseSnafu)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::Error;
+
+    fn parsed(input: &str) -> (String, String) {
+        let (code, state) = parse_redirect(input).unwrap();
+        (code.secret().clone(), state.secret().clone())
+    }
+
+    #[test]
+    fn parses_redirect_urls_and_queries() {
+        let expected = ("abc".to_string(), "xyz".to_string());
+        assert_eq!(
+            parsed("http://localhost:8080/redirect?code=abc&state=xyz#_=_"),
+            expected
+        );
+        assert_eq!(parsed("  ?state=xyz&code=abc\n"), expected);
+        assert_eq!(parsed("code=abc&state=xyz"), expected);
+    }
+
+    #[test]
+    fn refuses_bare_codes() {
+        let error = parse_redirect("abc").unwrap_err();
+        assert!(matches!(error, Error::MissingState), "{error:?}");
+    }
+
+    #[test]
+    fn reports_denied_access() {
+        let error = parse_redirect(
+            "http://localhost:8080/redirect?error=access_denied&error_description=Nope",
+        )
+        .unwrap_err();
+        assert!(
+            matches!(
+                &error,
+                Error::AuthorizationDenied { error, description }
+                    if error == "access_denied" && description.as_deref() == Some("Nope")
+            ),
+            "{error:?}"
+        );
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn checks_the_state_unless_opted_out() {
+        use crate::{testing::MockTumblr, TumblrClient};
+
+        let mock = MockTumblr::start().await.unwrap();
+        let builder = || TumblrClient::builder(mock.credentials()).endpoints(mock.endpoints());
+
+        let pending = builder().begin_authorization();
+        let error = builder()
+            .complete_authorization(pending, "?code=abc&state=forged")
+            .await
+            .unwrap_err();
+        assert!(matches!(error, Error::StateDoNotMatch { .. }), "{error:?}");
+
+        let pending = builder().begin_authorization();
+        let client = builder()
+            .complete_authorization_with_code(pending, "abc")
+            .await
+            .unwrap();
+        assert!(client.is_authorized());
+    }
+}
@@ -0,0 +1,299 @@
+use std::{net::SocketAddr, time::Duration};
+
+use oauth2::{AuthorizationCode, CsrfToken};
+use reqwest::Url;
+use snafu::prelude::*;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+    task::JoinSet,
+};
+
+use super::{
+    authorization::parse_query, AuthorizationTimeoutSnafu, Error, RedirectServerSnafu, Result,
+    UrlParseSnafu,
+};
+
+const LANDING_PAGE: &str = "Go back to your terminal :)";
+const DENIED_PAGE: &str = "Authorization was denied, you can close this page.";
+/// How long a connection gets to send its request line.
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long to wait after a failed accept, multiplied by how many failed in a row.
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(50);
+/// Failed accepts in a row before giving up.
+const MAX_ACCEPT_ERRORS: u32 = 10;
+
+/// The local server [`TumblrClientBuilder::authorize`](super::TumblrClientBuilder::authorize)
+/// waits on for Tumblr to send the user back.
+///
+/// The redirect URL itself comes from [`Endpoints::redirect`](crate::endpoints::Endpoints), and
+/// has to match the one in the app's settings. Requests to other paths, like the browser asking
+/// for `/favicon.ico`, get a 404 and are otherwise ignored, as are requests to the redirect URL
+/// without a code or an error, which get a 400.
+#[derive(Debug, Clone)]
+pub struct RedirectListener {
+    /// Where to listen, defaults to `127.0.0.1` on the redirect URL's port.
+    pub address: Option<SocketAddr>,
+    /// HTML shown in the browser once authorized.
+    pub landing_page: String,
+    /// HTML shown in the browser when the user denied access.
+    pub denied_page: String,
+    /// How long to wait for the user, forever by default.
+    pub timeout: Option<Duration>,
+}
+
+impl Default for RedirectListener {
+    fn default() -> Self {
+        Self {
+            address: None,
+            landing_page: LANDING_PAGE.to_string(),
+            denied_page: DENIED_PAGE.to_string(),
+            timeout: None,
+        }
+    }
+}
+
+impl RedirectListener {
+    pub fn with_address(mut self, address: SocketAddr) -> Self {
+        self.address = Some(address);
+        self
+    }
+
+    pub fn with_landing_page(mut self, landing_page: impl Into<String>) -> Self {
+        self.landing_page = landing_page.into();
+        self
+    }
+
+    pub fn with_denied_page(mut self, denied_page: impl Into<String>) -> Self {
+        self.denied_page = denied_page.into();
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Waits for Tumblr to send the user back to `redirect_url`.
+    pub(crate) async fn listen(
+        &self,
+        redirect_url: &Url,
+    ) -> Result<(AuthorizationCode, CsrfToken)> {
+        let address = match self.address {
+            Some(address) => address,
+            None => {
+                let port = redirect_url
+                    .port_or_known_default()
+                    .context(UrlParseSnafu)?;
+                SocketAddr::from(([127, 0, 0, 1], port))
+            }
+        };
+        let listener = TcpListener::bind(address)
+            .await
+            .context(RedirectServerSnafu)?;
+
+        match self.timeout {
+            Some(timeout) => tokio::time::timeout(timeout, self.accept(&listener, redirect_url))
+                .await
+                .ok()
+                .context(AuthorizationTimeoutSnafu)?,
+            None => self.accept(&listener, redirect_url).await,
+        }
+    }
+
+    /// Handles each connection in its own task, so a browser's idle preconnect doesn't hold up
+    /// the one carrying the code.
+    async fn accept(
+        &self,
+        listener: &TcpListener,
+        redirect_url: &Url,
+    ) -> Result<(AuthorizationCode, CsrfToken)> {
+        let mut connections = JoinSet::new();
+        let mut accept_errors = 0;
+        loop {
+            tokio::select! {
+                accepted = listener.accept() => {
+                    let stream = match accepted {
+                        Ok((stream, _)) => stream,
+                        // Such as running out of file descriptors, which may clear up.
+                        Err(error) => {
+                            accept_errors += 1;
+                            if accept_errors >= MAX_ACCEPT_ERRORS {
+                                return Err(error).context(RedirectServerSnafu);
+                            }
+                            tokio::time::sleep(ACCEPT_BACKOFF * accept_errors).await;
+                            continue;
+                        }
+                    };
+                    accept_errors = 0;
+                    connections.spawn(handle_connection(
+                        stream,
+                        redirect_url.path().to_string(),
+                        self.landing_page.clone(),
+                        self.denied_page.clone(),
+                    ));
+                }
+                Some(handled) = connections.join_next() => {
+                    if let Ok(Some(redirect)) = handled {
+                        return redirect;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Answers one connection, `None` unless it was the redirect.
+async fn handle_connection(
+    stream: TcpStream,
+    redirect_path: String,
+    landing_page: String,
+    denied_page: String,
+) -> Option<Result<(AuthorizationCode, CsrfToken)>> {
+    let mut stream = BufReader::new(stream);
+
+    let mut request_line = String::new();
+    let read = tokio::time::timeout(READ_TIMEOUT, stream.read_line(&mut request_line)).await;
+    if !matches!(read, Ok(Ok(_))) {
+        return None;
+    }
+    let target = request_line.split_whitespace().nth(1).unwrap_or_default();
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+
+    if path != redirect_path {
+        // Whatever else the browser asks for, such as `/favicon.ico`.
+        let _ = respond(stream, "404 Not Found", "").await;
+        return None;
+    }
+    match parse_query(query) {
+        Ok(Some(redirect)) => Some(
+            respond(stream, "200 OK", &landing_page)
+                .await
+                .map(|_| redirect),
+        ),
+        Err(error @ Error::AuthorizationDenied { .. }) => Some(
+            respond(stream, "200 OK", &denied_page)
+                .await
+                .and(Err(error)),
+        ),
+        // Neither a code nor an error, or a query that can't be read, isn't from Tumblr.
+        _ => {
+            let _ = respond(stream, "400 Bad Request", "").await;
+            None
+        }
+    }
+}
+
+async fn respond(mut stream: BufReader<TcpStream>, status: &str, body: &str) -> Result<()> {
+    let response = format!(
+        "HTTP/1.1 {status}\r\ncontent-type: text/html; charset=utf-8\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+        body.len(),
+    );
+    stream
+        .write_all(response.as_bytes())
+        .await
+        .context(RedirectServerSnafu)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+        task::JoinHandle,
+    };
+
+    use super::*;
+
+    /// Starts listening on a free port, returning its address and the listener's result.
+    fn listen(
+        listener: RedirectListener,
+    ) -> (
+        SocketAddr,
+        JoinHandle<Result<(AuthorizationCode, CsrfToken)>>,
+    ) {
+        let address = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let redirect_url = Url::parse(&format!("http://{address}/redirect")).unwrap();
+        let listener = listener.with_address(address);
+        let handle = tokio::spawn(async move { listener.listen(&redirect_url).await });
+        (address, handle)
+    }
+
+    /// Sends a `GET` for `target` once the listener is up, returning the response status line.
+    async fn get(address: SocketAddr, target: &str) -> String {
+        let mut stream = loop {
+            match TcpStream::connect(address).await {
+                Ok(stream) => break stream,
+                Err(_) => tokio::time::sleep(Duration::from_millis(5)).await,
+            }
+        };
+        let request = format!("GET {target} HTTP/1.1\r\nhost: {address}\r\n\r\n");
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response.lines().next().unwrap_or_default().to_string()
+    }
+
+    #[tokio::test]
+    async fn ignores_other_paths_until_the_redirect() {
+        let (address, handle) = listen(RedirectListener::default());
+        assert_eq!(get(address, "/favicon.ico").await, "HTTP/1.1 404 Not Found");
+        assert_eq!(
+            get(address, "/redirect?code=abc&state=xyz").await,
+            "HTTP/1.1 200 OK"
+        );
+        let (code, state) = handle.await.unwrap().unwrap();
+        assert_eq!(code.secret(), "abc");
+        assert_eq!(state.secret(), "xyz");
+    }
+
+    #[tokio::test]
+    async fn keeps_waiting_after_a_malformed_redirect() {
+        let (address, handle) = listen(RedirectListener::default());
+        assert_eq!(
+            get(address, "/redirect?code=a&code=b").await,
+            "HTTP/1.1 400 Bad Request"
+        );
+        assert_eq!(get(address, "/redirect").await, "HTTP/1.1 400 Bad Request");
+        assert_eq!(
+            get(address, "/redirect?code=abc&state=xyz").await,
+            "HTTP/1.1 200 OK"
+        );
+        assert_eq!(handle.await.unwrap().unwrap().0.secret(), "abc");
+    }
+
+    #[tokio::test]
+    async fn reports_denied_access() {
+        let (address, handle) = listen(RedirectListener::default());
+        assert_eq!(
+            get(
+                address,
+                "/redirect?error=access_denied&error_description=No"
+            )
+            .await,
+            "HTTP/1.1 200 OK"
+        );
+        let error = handle.await.unwrap().unwrap_err();
+        assert!(
+            matches!(
+                &error,
+                Error::AuthorizationDenied { error, description }
+                    if error == "access_denied" && description.as_deref() == Some("No")
+            ),
+            "{error:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn times_out() {
+        let (_, handle) =
+            listen(RedirectListener::default().with_timeout(Duration::from_millis(50)));
+        let error = handle.await.unwrap().unwrap_err();
+        assert!(matches!(error, Error::AuthorizationTimeout), "{error:?}");
+    }
+}
@@ -0,0 +1,227 @@
+use std::{
+    env, fmt,
+    path::PathBuf,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use snafu::prelude::*;
+
+use super::{DeserializeClientCacheFileSnafu, Error, Result, TumblrClientTokens};
+
+const TOKENS_VAR: &str = "TUMBLR_TOKENS";
+
+/// Where a [`TumblrClient`](crate::TumblrClient) keeps its tokens between runs.
+///
+/// Set one with [`TumblrClientBuilder::token_store`](super::TumblrClientBuilder::token_store)
+/// and it's saved to after authorizing and after every refresh, so a long-running client never
+/// loses a refreshed token.
+///
+/// ```no_run
+/// # use tumblr_api::{auth::{read_credentials, FileTokenStore}, TumblrClient};
+/// # async fn connect() -> Result<(), Box<dyn std::error::Error>> {
+/// let client = TumblrClient::builder(read_credentials()?)
+///     .token_store(FileTokenStore::new("client.json"))
+///     .load_or_authorize()
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+pub trait TokenStore: fmt::Debug + Send + Sync {
+    /// The stored tokens, `None` if nothing has been stored yet.
+    fn load(&self) -> Result<Option<TumblrClientTokens>>;
+
+    fn save(&self, tokens: &TumblrClientTokens) -> Result<()>;
+}
+
+/// Lets the caller keep a handle on a store it gave to the client.
+impl<T: TokenStore + ?Sized> TokenStore for Arc<T> {
+    fn load(&self) -> Result<Option<TumblrClientTokens>> {
+        (**self).load()
+    }
+
+    fn save(&self, tokens: &TumblrClientTokens) -> Result<()> {
+        (**self).save(tokens)
+    }
+}
+
+/// Stores tokens as JSON in a file, the same format as
+/// [`TumblrClient::save_to_file`](crate::TumblrClient::save_to_file).
+#[derive(Debug, Clone)]
+pub struct FileTokenStore {
+    path: PathBuf,
+}
+
+impl FileTokenStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl TokenStore for FileTokenStore {
+    fn load(&self) -> Result<Option<TumblrClientTokens>> {
+        if !self.path.exists() {
+            return Ok(None);
+        }
+        TumblrClientTokens::from_file(self.path.clone()).map(Some)
+    }
+
+    fn save(&self, tokens: &TumblrClientTokens) -> Result<()> {
+        tokens.save_to_file(self.path.clone())
+    }
+}
+
+/// Keeps tokens in memory, for tests or for handing them to something else to persist.
+#[derive(Debug, Default)]
+pub struct MemoryTokenStore {
+    tokens: Mutex<Option<TumblrClientTokens>>,
+}
+
+impl MemoryTokenStore {
+    pub fn new(tokens: TumblrClientTokens) -> Self {
+        Self {
+            tokens: Mutex::new(Some(tokens)),
+        }
+    }
+
+    /// The most recently saved tokens.
+    pub fn tokens(&self) -> Option<TumblrClientTokens> {
+        self.tokens
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+}
+
+impl TokenStore for MemoryTokenStore {
+    fn load(&self) -> Result<Option<TumblrClientTokens>> {
+        Ok(self.tokens())
+    }
+
+    fn save(&self, tokens: &TumblrClientTokens) -> Result<()> {
+        *self.tokens.lock().unwrap_or_else(PoisonError::into_inner) = Some(tokens.clone());
+        Ok(())
+    }
+}
+
+/// Reads tokens as JSON from an environment variable, `TUMBLR_TOKENS` by default.
+///
+/// The variable is only read. Refreshed tokens are kept in memory and take its place for the
+/// rest of the process, so they don't outlive it.
+#[derive(Debug)]
+pub struct EnvTokenStore {
+    name: String,
+    saved: Mutex<Option<TumblrClientTokens>>,
+}
+
+impl Default for EnvTokenStore {
+    fn default() -> Self {
+        Self::new(TOKENS_VAR)
+    }
+}
+
+impl EnvTokenStore {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            saved: Mutex::default(),
+        }
+    }
+}
+
+impl TokenStore for EnvTokenStore {
+    fn load(&self) -> Result<Option<TumblrClientTokens>> {
+        if let Some(tokens) = self
+            .saved
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+        {
+            return Ok(Some(tokens));
+        }
+
+        match env::var(&self.name) {
+            Ok(tokens) => serde_json::from_str(&tokens)
+                .map(Some)
+                .context(DeserializeClientCacheFileSnafu),
+            Err(env::VarError::NotPresent) => Ok(None),
+            Err(source) => Err(Error::TokenStore {
+                source: Box::new(source),
+            }),
+        }
+    }
+
+    fn save(&self, tokens: &TumblrClientTokens) -> Result<()> {
+        *self.saved.lock().unwrap_or_else(PoisonError::into_inner) = Some(tokens.clone());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use chrono::{SubsecRound, Utc};
+    use oauth2::{AccessToken, RefreshToken};
+
+    use super::*;
+
+    #[test]
+    fn file_store_round_trips() {
+        let path = env::temp_dir().join(format!("tumblr-api-tokens-{}.json", process::id()));
+        let store = FileTokenStore::new(&path);
+        let _ = fs::remove_file(&path);
+        assert!(store.load().unwrap().is_none());
+
+        let tokens = TumblrClientTokens {
+            access_token: AccessToken::new("access".to_string()),
+            refresh_at: Utc::now().trunc_subsecs(0),
+            refresh_token: Some(RefreshToken::new("refresh".to_string())),
+        };
+        store.save(&tokens).unwrap();
+        let loaded = store.load().unwrap().unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.access_token.secret(), "access");
+        assert_eq!(loaded.refresh_at, tokens.refresh_at);
+        assert_eq!(loaded.refresh_token.unwrap().secret(), "refresh");
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn tokens_are_saved_after_authorizing_and_refreshing() {
+        use crate::{testing::MockTumblr, TumblrClient};
+
+        let mock = MockTumblr::start().await.unwrap();
+        let store = Arc::new(MemoryTokenStore::default());
+        let builder = TumblrClient::builder(mock.credentials())
+            .endpoints(mock.endpoints())
+            .token_store(store.clone());
+        let pending = builder.begin_authorization();
+        let redirect = format!("?code=abc&state={}", pending.csrf_state.secret());
+        let client = builder
+            .complete_authorization(pending, &redirect)
+            .await
+            .unwrap();
+
+        let authorized = store.tokens().unwrap();
+        assert_eq!(
+            authorized.access_token.secret(),
+            client.get_access_token().unwrap().secret()
+        );
+
+        client.refresh_token().await.unwrap();
+        let refreshed = store.tokens().unwrap();
+        assert_ne!(
+            refreshed.access_token.secret(),
+            authorized.access_token.secret()
+        );
+        assert_eq!(
+            refreshed.access_token.secret(),
+            client.get_access_token().unwrap().secret()
+        );
+        assert_ne!(
+            refreshed.refresh_token.unwrap().secret(),
+            authorized.refresh_token.unwrap().secret()
+        );
+    }
+}
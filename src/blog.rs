@@ -1,3 +1,5 @@
+use std::fmt;
+
 use chrono::{serde::ts_seconds, DateTime, Utc};
 use reqwest::Url;
 use serde::{Deserialize, Serialize};
@@ -6,15 +8,72 @@ use serde_with::skip_serializing_none;
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct TumblrUuid(String);
 
+impl TumblrUuid {
+    pub fn new(uuid: impl Into<String>) -> Self {
+        Self(uuid.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
 /// For Post creation, only the UUID field is required.
 #[skip_serializing_none]
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct BlogMention {
     uuid: TumblrUuid,
     name: Option<String>,
     url: Option<Url>,
 }
 
+impl BlogMention {
+    pub fn new(uuid: TumblrUuid) -> Self {
+        Self {
+            uuid,
+            name: None,
+            url: None,
+        }
+    }
+
+    pub fn uuid(&self) -> &TumblrUuid {
+        &self.uuid
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Falls back to the blog's tumblr.com address when only the name is known.
+    pub fn url(&self) -> Option<Url> {
+        self.url
+            .clone()
+            .or_else(|| Url::parse(&format!("https://{}.tumblr.com/", self.name.as_ref()?)).ok())
+    }
+}
+
+/// The short form of a blog included with posts, such as in a reblog trail.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TumblrBlogSummary {
+    pub name: String,
+    pub uuid: TumblrUuid,
+    pub title: Option<String>,
+    pub url: Option<Url>,
+    /// `false` once the blog has been deactivated.
+    pub active: Option<bool>,
+    pub theme: Option<TumblrBlogTheme>,
+}
+
+impl TumblrBlogSummary {
+    pub fn mention(&self) -> BlogMention {
+        BlogMention {
+            uuid: self.uuid.clone(),
+            name: Some(self.name.clone()),
+            url: self.url.clone(),
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub enum TumblrBlogId {
     Uuid(TumblrUuid),
@@ -22,12 +81,12 @@ pub enum TumblrBlogId {
     BlogName(String),
 }
 
-impl TumblrBlogId {
-    pub fn to_string(self) -> String {
+impl fmt::Display for TumblrBlogId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            TumblrBlogId::Uuid(uuid) => uuid.0,
-            TumblrBlogId::Hostname(hostname) => hostname,
-            TumblrBlogId::BlogName(blog_name) => blog_name,
+            TumblrBlogId::Uuid(uuid) => f.write_str(&uuid.0),
+            TumblrBlogId::Hostname(hostname) => f.write_str(hostname),
+            TumblrBlogId::BlogName(blog_name) => f.write_str(blog_name),
         }
     }
 }
@@ -45,14 +104,14 @@ pub struct TumblrBlogAvatar {
     pub url: Url,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
 #[serde(rename_all = "snake_case")]
 pub enum AvatarShape {
     Square,
     Circle,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct TumblrBlogTheme {
     pub header_full_width: u16,
     pub header_full_height: u16,
@@ -0,0 +1,75 @@
+use reqwest::Url;
+
+const API_BASE_URL: &str = "https://api.tumblr.com/v2/";
+const AUTHORIZE_URL: &str = "https://www.tumblr.com/oauth2/authorize";
+const TOKEN_URL: &str = "https://api.tumblr.com/v2/oauth2/token";
+const REDIRECT_URL: &str = "http://localhost:8080/";
+
+/// Where [`TumblrClient`](crate::TumblrClient) sends its requests.
+///
+/// Defaults to Tumblr itself, tests can point it at a local server with [`Endpoints::local`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Endpoints {
+    /// Prefix of every API path, such as `https://api.tumblr.com/v2/`.
+    pub api_base: Url,
+    /// The page users are sent to to authorize the app.
+    pub authorize: Url,
+    pub token: Url,
+    /// Where Tumblr sends users back to after authorizing, must match the app's settings.
+    pub redirect: Url,
+}
+
+impl Default for Endpoints {
+    fn default() -> Self {
+        Self {
+            api_base: Url::parse(API_BASE_URL).expect("API base URL failed to be created."),
+            authorize: Url::parse(AUTHORIZE_URL).expect("Auth URL failed to be created."),
+            token: Url::parse(TOKEN_URL).expect("Token URL failed to be created."),
+            redirect: Url::parse(REDIRECT_URL).expect("Redirect URL failed to be created."),
+        }
+    }
+}
+
+impl Endpoints {
+    /// Serves both the API and OAuth from `base`, laid out like Tumblr's: the API under `v2/`,
+    /// the token endpoint at `v2/oauth2/token` and authorization at `oauth2/authorize`.
+    pub fn local(base: &Url) -> Result<Self, oauth2::url::ParseError> {
+        let base = with_trailing_slash(base.clone());
+        Ok(Self {
+            api_base: base.join("v2/")?,
+            authorize: base.join("oauth2/authorize")?,
+            token: base.join("v2/oauth2/token")?,
+            ..Default::default()
+        })
+    }
+
+    /// `api_base` must end with a slash for paths to be joined onto it, so one is added if
+    /// it's missing.
+    pub fn with_api_base(mut self, api_base: Url) -> Self {
+        self.api_base = with_trailing_slash(api_base);
+        self
+    }
+
+    pub fn with_authorize(mut self, authorize: Url) -> Self {
+        self.authorize = authorize;
+        self
+    }
+
+    pub fn with_token(mut self, token: Url) -> Self {
+        self.token = token;
+        self
+    }
+
+    pub fn with_redirect(mut self, redirect: Url) -> Self {
+        self.redirect = redirect;
+        self
+    }
+}
+
+fn with_trailing_slash(mut url: Url) -> Url {
+    if !url.path().ends_with('/') {
+        let path = format!("{}/", url.path());
+        url.set_path(&path);
+    }
+    url
+}
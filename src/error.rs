@@ -0,0 +1,200 @@
+use std::{fmt, io};
+
+use reqwest::StatusCode;
+use serde::Deserialize;
+use snafu::prelude::*;
+
+use crate::{auth, requests::TumblrResponseMeta};
+
+pub type Result<T, E = Error> = core::result::Result<T, E>;
+
+/// Anything that can go wrong while talking to Tumblr.
+#[derive(Debug, Snafu)]
+#[snafu(visibility(pub))]
+pub enum Error {
+    #[snafu(display("Failed to build request URL: {}", source))]
+    UrlBuild { source: oauth2::url::ParseError },
+    #[snafu(display("Failed to encode request: {}", source))]
+    Encode { source: serde_json::Error },
+    #[snafu(display("Failed to read media attachment: {}", source))]
+    Media { source: io::Error },
+    #[snafu(display("HTTP request failed: {}", source))]
+    Http { source: reqwest::Error },
+    #[snafu(display("{}", source))]
+    Api { source: ApiError },
+    #[snafu(display("Failed to decode response: {}", source))]
+    Decode {
+        source: serde_json::Error,
+        /// The response as it was received.
+        body: String,
+    },
+    #[snafu(display("Failed to refresh access token: {}", source))]
+    Auth { source: auth::Error },
+    /// The request needs OAuth but the client only has an API key.
+    #[snafu(display("This request needs an OAuth authorized client."))]
+    OAuthRequired,
+    #[cfg(feature = "testing")]
+    #[snafu(display("Failed to write cassette: {}", source))]
+    CassetteWrite { source: io::Error },
+    #[cfg(feature = "testing")]
+    #[snafu(display("No recorded response for {} {}", method, path))]
+    CassetteMiss { method: String, path: String },
+}
+
+impl Error {
+    /// The API error, if Tumblr rejected the request.
+    pub fn api_error(&self) -> Option<&ApiError> {
+        match self {
+            Error::Api { source } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// One entry of the `errors` array Tumblr sends with a failed request.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ApiErrorDetail {
+    pub title: Option<String>,
+    /// Tumblr's own error code, more specific than the status.
+    pub code: Option<u32>,
+    pub detail: Option<String>,
+}
+
+/// A request Tumblr responded to with an error status.
+///
+/// https://www.tumblr.com/docs/en/api/v2#errors
+#[derive(Debug, Clone)]
+pub struct ApiError {
+    pub status: StatusCode,
+    pub msg: String,
+    pub errors: Vec<ApiErrorDetail>,
+    /// The response as it was received.
+    pub body: String,
+}
+
+#[derive(Deserialize)]
+struct ErrorResponse {
+    meta: Option<TumblrResponseMeta>,
+    #[serde(default)]
+    errors: Vec<ApiErrorDetail>,
+}
+
+impl ApiError {
+    /// Reads the error out of a response body, falling back to the HTTP status if the body
+    /// isn't Tumblr's usual error format.
+    pub fn from_response(status: StatusCode, body: String) -> Self {
+        let (meta, errors) = match serde_json::from_str::<ErrorResponse>(&body) {
+            Ok(response) => (response.meta, response.errors),
+            Err(_) => (None, Vec::new()),
+        };
+        let (status, msg) = match meta {
+            Some(meta) => (
+                StatusCode::from_u16(meta.status).unwrap_or(status),
+                meta.msg,
+            ),
+            None => (
+                status,
+                status.canonical_reason().unwrap_or_default().to_string(),
+            ),
+        };
+
+        Self {
+            status,
+            msg,
+            errors,
+            body,
+        }
+    }
+
+    /// Every Tumblr error code in the response.
+    pub fn codes(&self) -> impl Iterator<Item = u32> + '_ {
+        self.errors.iter().filter_map(|error| error.code)
+    }
+
+    pub fn has_code(&self, code: u32) -> bool {
+        self.codes().any(|existing| existing == code)
+    }
+
+    pub fn is_unauthorized(&self) -> bool {
+        self.status == StatusCode::UNAUTHORIZED
+    }
+
+    pub fn is_forbidden(&self) -> bool {
+        self.status == StatusCode::FORBIDDEN
+    }
+
+    pub fn is_not_found(&self) -> bool {
+        self.status == StatusCode::NOT_FOUND
+    }
+
+    pub fn is_rate_limited(&self) -> bool {
+        self.status == StatusCode::TOO_MANY_REQUESTS
+    }
+
+    pub fn is_server_error(&self) -> bool {
+        self.status.is_server_error()
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Tumblr responded with {} {}",
+            self.status.as_u16(),
+            self.msg
+        )?;
+        for error in &self.errors {
+            let description = error.detail.as_ref().or(error.title.as_ref());
+            match (error.code, description) {
+                (Some(code), Some(description)) => write!(f, "; {description} ({code})")?,
+                (None, Some(description)) => write!(f, "; {description}")?,
+                (Some(code), None) => write!(f, "; code {code}")?,
+                (None, None) => (),
+            }
+        }
+        f.write_str(".")
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_tumblr_errors() {
+        let body = r#"{
+            "meta": { "status": 401, "msg": "Unauthorized" },
+            "response": [],
+            "errors": [
+                { "title": "Unauthorized", "code": 1016, "detail": "Unable to authorize" },
+                { "detail": "No code" }
+            ]
+        }"#;
+        let error = ApiError::from_response(StatusCode::BAD_REQUEST, body.to_string());
+        assert_eq!(error.status, StatusCode::UNAUTHORIZED);
+        assert!(error.is_unauthorized());
+        assert_eq!(error.msg, "Unauthorized");
+        assert_eq!(error.codes().collect::<Vec<_>>(), [1016]);
+        assert!(error.has_code(1016));
+        assert_eq!(
+            error.errors[0].detail.as_deref(),
+            Some("Unable to authorize")
+        );
+        assert_eq!(error.errors[1].title, None);
+        assert_eq!(error.body, body);
+    }
+
+    #[test]
+    fn falls_back_to_the_http_status() {
+        let body = "<html>Bad Gateway</html>";
+        let error = ApiError::from_response(StatusCode::BAD_GATEWAY, body.to_string());
+        assert_eq!(error.status, StatusCode::BAD_GATEWAY);
+        assert!(error.is_server_error());
+        assert_eq!(error.msg, "Bad Gateway");
+        assert!(error.errors.is_empty());
+        assert_eq!(error.body, body);
+    }
+}
@@ -1,7 +1,12 @@
 pub mod auth;
 pub mod blog;
+pub mod endpoints;
+pub mod error;
 pub mod macros;
 pub mod post;
 pub mod requests;
+#[cfg(feature = "testing")]
+pub mod testing;
 
 pub use self::auth::TumblrClient;
+pub use self::error::Error;
@@ -1,9 +1,9 @@
+/// Builds [`Tags`](crate::post::Tags) from any values that can be displayed.
+///
+/// Returns an error if a tag is empty or contains a comma.
 #[macro_export]
 macro_rules! tags {
-    ($tag: literal, $($tags: literal),*$(,)?) => {
-        concat!($tag, ",", tags!($($tags),*))
-    };
-    ($tag: literal) => {
-        $tag
+    ($($tag: expr),*$(,)?) => {
+        $crate::post::Tags::try_from_iter([$(::std::string::ToString::to_string(&$tag)),*])
     };
 }
@@ -1,10 +1,37 @@
+mod ask;
+mod builder;
 mod content;
+mod dates;
+mod html;
+mod markdown;
+mod offsets;
+mod tags;
+mod text;
+mod validate;
+#[cfg(any(feature = "html", feature = "markdown"))]
+mod writer;
 
+use std::collections::HashMap;
+
+use chrono::{serde::ts_seconds_option, DateTime, Utc};
 use reqwest::Url;
 use serde::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
 
+use crate::blog::{BlogMention, TumblrBlogSummary};
+
+pub use self::ask::*;
+pub use self::builder::*;
 pub use self::content::*;
+pub use self::dates::{format_tumblr_date, parse_tumblr_date};
+pub use self::html::*;
+pub use self::markdown::*;
+pub use self::offsets::*;
+pub use self::tags::*;
+pub use self::text::*;
+pub use self::validate::*;
+#[cfg(any(feature = "html", feature = "markdown"))]
+pub use self::writer::DroppedContent;
 
 /// https://www.tumblr.com/docs/en/api/v2#note-about-post-states
 #[derive(Debug, Default, Serialize, Deserialize)]
@@ -12,10 +39,22 @@ pub use self::content::*;
 pub enum PostState {
     #[default]
     Published,
+    /// Fetched posts say `queued`, while creating one takes `queue`.
+    #[serde(alias = "queued")]
     Queue,
     Draft,
     Private,
     Unapproved,
+    /// An ask or submission waiting in the inbox.
+    Submission,
+}
+
+/// Whether a queued post goes out with the queue or at a set time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum QueuedState {
+    Queued,
+    Scheduled,
 }
 
 #[derive(Debug, Default, Serialize, Deserialize)]
@@ -28,11 +67,149 @@ pub enum PostInteractability {
     NoOne,
 }
 
-#[derive(Debug, Default, Serialize, Deserialize)]
-pub struct PostLayout(());
+/// https://www.tumblr.com/docs/npf#layout-block-display-mode-weighted
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum LayoutRowMode {
+    Weighted,
+}
 
+/// A row of blocks, shown side by side.
 #[skip_serializing_none]
-#[derive(Debug, Default, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LayoutRow {
+    /// Indices into the post's content.
+    pub blocks: Vec<u32>,
+    pub mode: Option<LayoutRowMode>,
+}
+
+/// https://www.tumblr.com/docs/npf#layout-blocks
+#[skip_serializing_none]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum PostLayout {
+    /// https://www.tumblr.com/docs/npf#layout-block-type-rows
+    Rows {
+        display: Vec<LayoutRow>,
+        /// The last block shown above the "keep reading" fold.
+        truncate_after: Option<u32>,
+    },
+    /// Deprecated by Tumblr in favour of `truncate_after` on [`PostLayout::Rows`].
+    ///
+    /// https://www.tumblr.com/docs/npf#layout-block-type-condensed
+    Condensed {
+        blocks: Option<Vec<u32>>,
+        truncate_after: Option<u32>,
+    },
+    /// The blocks which make up an ask, answered by the blocks after them.
+    ///
+    /// https://www.tumblr.com/docs/npf#layout-block-type-ask
+    Ask {
+        blocks: Vec<u32>,
+        /// Missing when the ask was sent anonymously.
+        attribution: Option<Box<AskAttribution>>,
+    },
+    /// A layout this crate doesn't know about yet.
+    #[serde(other)]
+    Unknown,
+}
+
+/// Who sent an ask.
+#[skip_serializing_none]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum AskAttribution {
+    Blog { url: Option<Url>, blog: BlogMention },
+}
+
+impl PostLayout {
+    /// One block per row, with a "keep reading" fold after `truncate_after`.
+    pub fn truncated(block_count: usize, truncate_after: u32) -> Self {
+        Self::Rows {
+            display: (0..block_count as u32)
+                .map(|block| LayoutRow {
+                    blocks: vec![block],
+                    mode: None,
+                })
+                .collect(),
+            truncate_after: Some(truncate_after),
+        }
+    }
+}
+
+/// The order blocks are shown in, as described by a post's layouts.
+#[derive(Debug)]
+pub(crate) struct DisplayRows {
+    pub rows: Vec<Vec<usize>>,
+    /// Index into `rows` of the last row above the "keep reading" fold.
+    pub truncate_after: Option<usize>,
+}
+
+impl DisplayRows {
+    /// Blocks missing from the rows layout are shown after it, so nothing is lost.
+    pub fn new(layout: &[PostLayout], block_count: usize) -> Self {
+        let mut rows = Vec::new();
+        let mut truncate_after_block = None;
+        for layout in layout {
+            match layout {
+                PostLayout::Rows {
+                    display,
+                    truncate_after,
+                } => {
+                    rows.extend(display.iter().map(|row| {
+                        row.blocks
+                            .iter()
+                            .map(|block| *block as usize)
+                            .filter(|block| *block < block_count)
+                            .collect::<Vec<_>>()
+                    }));
+                    truncate_after_block = truncate_after_block.or(*truncate_after);
+                }
+                PostLayout::Condensed { truncate_after, .. } => {
+                    truncate_after_block = truncate_after_block.or(*truncate_after);
+                }
+                PostLayout::Ask { .. } | PostLayout::Unknown => (),
+            }
+        }
+        rows.retain(|row| !row.is_empty());
+
+        let mut shown = vec![false; block_count];
+        rows.iter().flatten().for_each(|block| shown[*block] = true);
+        rows.extend(
+            (0..block_count)
+                .filter(|block| !shown[*block])
+                .map(|block| vec![block]),
+        );
+
+        let truncate_after = truncate_after_block
+            .and_then(|block| rows.iter().position(|row| row.contains(&(block as usize))));
+
+        Self {
+            rows,
+            truncate_after,
+        }
+    }
+}
+
+/// The content blocks of a post along with how they're laid out.
+#[derive(Debug, Default)]
+pub struct PostBody {
+    pub content: Vec<PostContent>,
+    pub layout: Vec<PostLayout>,
+}
+
+impl From<PostBody> for PostCreate {
+    fn from(value: PostBody) -> Self {
+        Self {
+            content: value.content,
+            layout: (!value.layout.is_empty()).then_some(value.layout),
+            ..Default::default()
+        }
+    }
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Default, Clone, Serialize)]
 pub struct ReblogInfo {
     pub parent_tumblelog_uuid: String,
     pub parent_post_id: u64,
@@ -50,9 +227,13 @@ pub struct PostCreate {
     pub content: Vec<PostContent>,
     pub layout: Option<Vec<PostLayout>>,
     pub state: Option<PostState>,
-    pub publish_on: Option<String>,
-    pub date: Option<String>,
-    pub tags: Option<String>,
+    /// When a queued post will be published.
+    #[serde(with = "dates::tumblr_date_option")]
+    pub publish_on: Option<DateTime<Utc>>,
+    /// Backdates the post.
+    #[serde(with = "dates::tumblr_date_option")]
+    pub date: Option<DateTime<Utc>>,
+    pub tags: Option<Tags>,
     pub source_url: Option<Url>,
     pub send_to_twitter: Option<bool>,
     pub is_private: Option<bool>,
@@ -62,63 +243,295 @@ pub struct PostCreate {
     pub reblog_info: Option<ReblogInfo>,
 }
 
+/// A post as returned by Tumblr.
+///
+/// Fields which are only sometimes present are optional, and any fields not modelled here are
+/// kept in `extra`.
+///
+/// https://www.tumblr.com/docs/en/api/v2#posts--retrieve-published-posts
 #[derive(Debug, Deserialize)]
 pub struct PostGet {
-    pub object_type: String,
+    pub object_type: Option<String>,
     #[serde(rename = "type")]
     pub post_type: String,
+    pub original_type: Option<String>,
     pub id: u64,
+    pub id_string: Option<String>,
+    pub blog_name: Option<String>,
+    pub blog: Option<TumblrBlogSummary>,
     pub tumblelog_uuid: String,
     pub parent_post_id: Option<String>,
     pub parent_tumblelog_uuid: Option<String>,
-    pub reblog_key: String,
-    pub original_type: String,
-    pub is_blocks_post_format: bool,
-    pub blog_name: String,
-    pub id_string: String,
-    pub is_blazed: bool,
-    pub is_blaze_pending: bool,
-    pub can_ignite: bool,
-    pub can_blaze: bool,
-    pub post_url: Url,
-    pub slug: String,
-    pub date: String,
-    pub timestamp: u32,
-    pub state: PostState,
-    pub tags: Vec<String>,
-    pub short_url: Url,
-    pub summary: String,
-    pub should_open_in_legacy: bool,
-    // recommended_source
-    // recommended_color
-    pub followed: bool,
-    pub liked: bool,
-    pub note_count: u32,
+    pub reblog_key: Option<String>,
+    /// Who wrote the post, on blogs with several members.
+    pub post_author: Option<String>,
+    pub is_blocks_post_format: Option<bool>,
+    pub post_url: Option<Url>,
+    pub short_url: Option<Url>,
+    pub slug: Option<String>,
+    #[serde(default, with = "dates::tumblr_date_option")]
+    pub date: Option<DateTime<Utc>>,
+    pub timestamp: Option<u32>,
+    pub state: Option<PostState>,
+    #[serde(default)]
+    pub tags: Tags,
+    pub summary: Option<String>,
+    pub should_open_in_legacy: Option<bool>,
+    pub recommended_source: Option<String>,
+    pub recommended_color: Option<String>,
+    pub followed: Option<bool>,
+    pub liked: Option<bool>,
+    pub note_count: Option<u32>,
+    /// Counts of each kind of note, which some endpoints send alongside `note_count`.
+    pub like_count: Option<u32>,
+    pub reblog_count: Option<u32>,
+    pub reply_count: Option<u32>,
+    /// Only included when notes were asked for.
+    pub notes: Option<Vec<PostNote>>,
+    pub asking_name: Option<String>,
+    pub asking_url: Option<Url>,
+    #[serde(default)]
     pub content: Vec<PostContent>,
+    #[serde(default)]
     pub layout: Vec<PostLayout>,
+    #[serde(default)]
     pub trail: Vec<PostTrail>,
-    //pub queued_state: Option<()>,
-    //pub scheduled_publish_time: Option<()>,
-    //pub publish_on: Option<()>,
-    pub can_like: bool,
-    pub interactability_reblog: PostInteractability,
-    pub interactability_blaze: PostInteractability,
-    pub can_reblog: bool,
-    pub can_send_in_message: bool,
-    pub muted: bool,
-    pub mute_end_timestamp: u32,
-    pub can_mute: bool,
+    pub queued_state: Option<QueuedState>,
+    #[serde(default, with = "ts_seconds_option")]
+    pub scheduled_publish_time: Option<DateTime<Utc>>,
+    #[serde(default, with = "dates::tumblr_date_option")]
+    pub publish_on: Option<DateTime<Utc>>,
+    pub reblogged_from_id: Option<String>,
+    pub reblogged_from_url: Option<Url>,
+    pub reblogged_from_name: Option<String>,
+    pub reblogged_from_title: Option<String>,
+    pub reblogged_from_uuid: Option<String>,
+    pub reblogged_from_can_message: Option<bool>,
+    pub reblogged_from_following: Option<bool>,
+    pub reblogged_root_id: Option<String>,
+    pub reblogged_root_url: Option<Url>,
+    pub reblogged_root_name: Option<String>,
+    pub reblogged_root_title: Option<String>,
+    pub reblogged_root_uuid: Option<String>,
+    pub reblogged_root_can_message: Option<bool>,
+    pub reblogged_root_following: Option<bool>,
+    pub can_like: Option<bool>,
+    pub can_reblog: Option<bool>,
+    pub can_reply: Option<bool>,
+    pub can_send_in_message: Option<bool>,
+    pub interactability_reblog: Option<PostInteractability>,
+    pub interactability_blaze: Option<PostInteractability>,
+    pub is_blazed: Option<bool>,
+    pub is_blaze_pending: Option<bool>,
+    pub can_ignite: Option<bool>,
+    pub can_blaze: Option<bool>,
+    pub muted: Option<bool>,
+    pub mute_end_timestamp: Option<u32>,
+    pub can_mute: Option<bool>,
+    /// Everything else Tumblr sent.
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PostNoteType {
+    Like,
+    Reblog,
+    Posted,
+    Reply,
+    #[serde(other)]
+    Unknown,
 }
 
+/// A like, reblog or reply on a post.
+#[derive(Debug, Deserialize)]
+pub struct PostNote {
+    #[serde(rename = "type")]
+    pub note_type: PostNoteType,
+    #[serde(default, with = "ts_seconds_option")]
+    pub timestamp: Option<DateTime<Utc>>,
+    pub blog_name: Option<String>,
+    pub blog_uuid: Option<String>,
+    pub blog_url: Option<Url>,
+    pub followed: Option<bool>,
+    /// The reblog's id, for reblogs.
+    pub post_id: Option<String>,
+    pub reblog_parent_blog_name: Option<String>,
+    pub reply_text: Option<String>,
+    pub added_text: Option<String>,
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+/// One post in a reblog chain, oldest first.
+///
+/// https://www.tumblr.com/docs/npf#the-reblog-trail
 #[derive(Debug, Deserialize)]
 pub struct PostTrail {
-    // blog
+    /// Missing when the blog has since been deleted, see `broken_blog_name`.
+    pub blog: Option<TumblrBlogSummary>,
+    pub broken_blog_name: Option<String>,
+    pub post: Option<PostTrailPost>,
     pub content: Vec<PostContent>,
+    #[serde(default)]
     pub layout: Vec<PostLayout>,
-    pub post: PostTrailId,
+    /// Whether this is the post that started the chain.
+    #[serde(default)]
+    pub is_root_item: bool,
+    /// Whether this is the content of the post the trail belongs to.
+    #[serde(default)]
+    pub is_current_item: bool,
+}
+
+impl PostTrail {
+    /// The blog's name, even if it's been deleted.
+    pub fn blog_name(&self) -> Option<&str> {
+        self.blog
+            .as_ref()
+            .map(|blog| blog.name.as_str())
+            .or(self.broken_blog_name.as_deref())
+    }
 }
 
 #[derive(Debug, Deserialize)]
-pub struct PostTrailId {
+pub struct PostTrailPost {
     pub id: String,
+    #[serde(default, with = "ts_seconds_option")]
+    pub timestamp: Option<DateTime<Utc>>,
+    pub is_commercial: Option<bool>,
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn deserializes_minimal_posts() {
+        let post: PostGet = serde_json::from_value(json!({
+            "type": "blocks",
+            "id": 1,
+            "tumblelog_uuid": "t:staff",
+        }))
+        .unwrap();
+        assert_eq!(post.id, 1);
+        assert_eq!(post.tumblelog_uuid, "t:staff");
+        assert_eq!(post.blog_name, None);
+        assert_eq!(post.reblog_key, None);
+        assert_eq!(post.post_url, None);
+        assert_eq!((post.date, post.timestamp), (None, None));
+        assert!(post.content.is_empty() && post.tags.is_empty());
+        assert!(post.extra.is_empty());
+        assert_eq!(post.reblog_info().reblog_key, "");
+    }
+
+    #[test]
+    fn deserializes_note_counts_and_unknown_fields() {
+        let post: PostGet = serde_json::from_value(json!({
+            "type": "blocks",
+            "id": 1,
+            "blog_name": "staff",
+            "tumblelog_uuid": "t:staff",
+            "reblog_key": "key",
+            "post_url": "https://staff.tumblr.com/post/1",
+            "date": "2023-07-21 19:05:07 GMT",
+            "timestamp": 1689966307,
+            "note_count": 12,
+            "like_count": 7,
+            "reblog_count": 4,
+            "reply_count": 1,
+            "is_nsfw": false,
+        }))
+        .unwrap();
+        assert_eq!(post.blog_name.as_deref(), Some("staff"));
+        assert_eq!(post.reblog_key.as_deref(), Some("key"));
+        assert_eq!(post.date.map(|date| date.timestamp()), Some(1689966307));
+        assert_eq!(post.timestamp, Some(1689966307));
+        assert_eq!(
+            (
+                post.note_count,
+                post.like_count,
+                post.reblog_count,
+                post.reply_count
+            ),
+            (Some(12), Some(7), Some(4), Some(1))
+        );
+        assert_eq!(post.extra["is_nsfw"], json!(false));
+    }
+
+    /// A reblog of a reblog, whose original blog has since been deleted.
+    const REBLOG_FIXTURE: &str = r#"{
+        "object_type": "post",
+        "type": "blocks",
+        "id": 300,
+        "id_string": "300",
+        "blog_name": "current",
+        "tumblelog_uuid": "t:current",
+        "reblog_key": "key300",
+        "parent_post_id": "200",
+        "parent_tumblelog_uuid": "t:middle",
+        "content": [{ "type": "text", "text": "Current." }],
+        "layout": [],
+        "trail": [
+            {
+                "broken_blog_name": "deleted-blog",
+                "post": { "id": "100", "timestamp": 1689966307 },
+                "content": [{ "type": "text", "text": "Root." }],
+                "layout": [],
+                "is_root_item": true
+            },
+            {
+                "blog": {
+                    "name": "middle",
+                    "uuid": "t:middle",
+                    "url": "https://middle.tumblr.com/",
+                    "active": true
+                },
+                "post": { "id": "200" },
+                "content": [{ "type": "text", "text": "Middle." }]
+            },
+            {
+                "blog": { "name": "current", "uuid": "t:current" },
+                "post": { "id": "300" },
+                "content": [{ "type": "text", "text": "Current." }],
+                "layout": [],
+                "is_current_item": true
+            }
+        ]
+    }"#;
+
+    #[test]
+    fn deserializes_trails_with_broken_blogs() {
+        let post: PostGet = serde_json::from_str(REBLOG_FIXTURE).unwrap();
+        let [root, middle, current] = post.trail.as_slice() else {
+            panic!("Expected three trail items, got {:?}.", post.trail);
+        };
+
+        assert!(root.blog.is_none());
+        assert_eq!(root.blog_name(), Some("deleted-blog"));
+        let root_post = root.post.as_ref().unwrap();
+        assert_eq!(root_post.id, "100");
+        assert_eq!(
+            root_post.timestamp.map(|timestamp| timestamp.timestamp()),
+            Some(1689966307)
+        );
+        assert!(matches!(
+            root.content.as_slice(),
+            [PostContent::Text { text, .. }] if text == "Root."
+        ));
+
+        assert_eq!(middle.blog_name(), Some("middle"));
+        assert_eq!(middle.broken_blog_name, None);
+        assert!(middle.layout.is_empty());
+        assert_eq!(current.blog_name(), Some("current"));
+
+        let flags = post
+            .trail
+            .iter()
+            .map(|item| (item.is_root_item, item.is_current_item))
+            .collect::<Vec<_>>();
+        assert_eq!(flags, [(true, false), (false, false), (false, true)]);
+    }
 }
@@ -0,0 +1,286 @@
+use crate::{
+    blog::{BlogMention, TumblrBlogId, TumblrUuid},
+    requests::{blog::posts::PostEditRequest, media::MediaAttachment},
+};
+
+use super::{AskAttribution, PostBuilder, PostContent, PostCreate, PostGet, PostLayout, PostState};
+
+impl PostGet {
+    /// The ask layout, when the post is an ask or an answer to one.
+    pub fn ask_layout(&self) -> Option<(&[u32], Option<&AskAttribution>)> {
+        self.layout.iter().find_map(|layout| match layout {
+            PostLayout::Ask {
+                blocks,
+                attribution,
+            } => Some((blocks.as_slice(), attribution.as_deref())),
+            _ => None,
+        })
+    }
+
+    pub fn is_ask(&self) -> bool {
+        self.ask_layout().is_some()
+    }
+
+    /// Whether the ask was sent without a blog attached.
+    pub fn is_anonymous_ask(&self) -> bool {
+        matches!(self.ask_layout(), Some((_, None)))
+    }
+
+    /// The blog that sent the ask, unless it was anonymous.
+    pub fn asker(&self) -> Option<&BlogMention> {
+        match self.ask_layout()? {
+            (_, Some(AskAttribution::Blog { blog, .. })) => Some(blog),
+            (_, None) => None,
+        }
+    }
+
+    /// The blocks that make up the ask itself.
+    pub fn ask_content(&self) -> Vec<&PostContent> {
+        self.ask_layout()
+            .map(|(blocks, _)| {
+                blocks
+                    .iter()
+                    .filter_map(|block| self.content.get(*block as usize))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Answers the ask, usually one fetched from the blog's submissions.
+    ///
+    /// `None` if the post isn't an ask, as editing it into one would replace its content.
+    pub fn answer(&self) -> Option<AnswerBuilder> {
+        let (_, attribution) = self.ask_layout()?;
+        let ask_content = self.ask_content().into_iter().cloned().collect::<Vec<_>>();
+        if ask_content.is_empty() {
+            return None;
+        }
+        Some(AnswerBuilder {
+            post: PostBuilder::new().state(PostState::Published),
+            blog_id: match &self.blog_name {
+                Some(blog_name) => TumblrBlogId::BlogName(blog_name.clone()),
+                None => TumblrBlogId::Uuid(TumblrUuid::new(self.tumblelog_uuid.clone())),
+            },
+            post_id: self.id.to_string(),
+            ask_content,
+            attribution: attribution.cloned().map(Box::new),
+        })
+    }
+}
+
+/// Publishes an answer to an ask by editing the ask post.
+///
+/// The ask's blocks are kept at the start of the post, followed by the answer.
+///
+/// ```no_run
+/// # use tumblr_api::post::PostGet;
+/// # fn answer(ask: &PostGet) -> Option<()> {
+/// let request = ask
+///     .answer()?
+///     .content(|answer| answer.paragraph(|p| p.text("Drink more water.")))
+///     .build_request();
+/// # Some(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct AnswerBuilder {
+    post: PostBuilder,
+    blog_id: TumblrBlogId,
+    post_id: String,
+    ask_content: Vec<PostContent>,
+    attribution: Option<Box<AskAttribution>>,
+}
+
+impl AnswerBuilder {
+    /// Adds to the answer. Other settings, like tags or queueing, can be changed here too.
+    pub fn content(mut self, answer: impl FnOnce(PostBuilder) -> PostBuilder) -> Self {
+        self.post = answer(self.post);
+        self
+    }
+
+    /// Any uploaded media is discarded, use [`AnswerBuilder::build_request`] to keep it.
+    pub fn build(self) -> PostCreate {
+        let (post, _) = self.into_parts();
+        post
+    }
+
+    pub fn build_request(self) -> PostEditRequest {
+        let (blog_id, post_id) = (self.blog_id.clone(), self.post_id.clone());
+        let (parameters, media) = self.into_parts();
+        PostEditRequest {
+            blog_id,
+            post_id,
+            parameters,
+            media,
+        }
+    }
+
+    fn into_parts(self) -> (PostCreate, Vec<MediaAttachment>) {
+        let request = self.post.build_request(self.blog_id);
+        let mut answer = request.parameters;
+        let ask_len = self.ask_content.len() as u32;
+
+        let mut layout = vec![PostLayout::Ask {
+            blocks: (0..ask_len).collect(),
+            attribution: self.attribution,
+        }];
+        layout.extend(
+            answer
+                .layout
+                .take()
+                .into_iter()
+                .flatten()
+                .map(|mut layout| {
+                    offset_blocks(&mut layout, ask_len);
+                    layout
+                }),
+        );
+
+        let mut content = self.ask_content;
+        content.append(&mut answer.content);
+
+        (
+            PostCreate {
+                content,
+                layout: Some(layout),
+                ..answer
+            },
+            request.media,
+        )
+    }
+}
+
+/// Shifts a layout's block indices, for when blocks are inserted before it.
+fn offset_blocks(layout: &mut PostLayout, offset: u32) {
+    let blocks: Vec<&mut u32> = match layout {
+        PostLayout::Rows {
+            display,
+            truncate_after,
+        } => display
+            .iter_mut()
+            .flat_map(|row| row.blocks.iter_mut())
+            .chain(truncate_after.as_mut())
+            .collect(),
+        PostLayout::Condensed {
+            blocks,
+            truncate_after,
+        } => blocks
+            .iter_mut()
+            .flatten()
+            .chain(truncate_after.as_mut())
+            .collect(),
+        PostLayout::Ask { blocks, .. } => blocks.iter_mut().collect(),
+        PostLayout::Unknown => Vec::new(),
+    };
+    for block in blocks {
+        *block += offset;
+    }
+}
+
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+    use serde_json::{json, Value};
+
+    use crate::{
+        blog::TumblrBlogId,
+        post::PostGet,
+        requests::blog::posts::{PostGetRequest, PostSubmissionsRequest},
+        testing::MockTumblr,
+    };
+
+    fn staff() -> TumblrBlogId {
+        TumblrBlogId::BlogName("staff".to_string())
+    }
+
+    /// Puts an ask in the mock's inbox and answers it, returning the answered post.
+    async fn answer_ask(attribution: Option<Value>) -> PostGet {
+        let mock = MockTumblr::start().await.unwrap();
+        mock.add_blog("staff");
+        let mut layout = json!({ "type": "ask", "blocks": [0] });
+        if let Some(attribution) = attribution {
+            layout["attribution"] = attribution;
+        }
+        let id = mock.add_post(
+            "staff",
+            json!({
+                "content": [{ "type": "text", "text": "What's your favourite colour?" }],
+                "layout": [layout],
+                "state": "submission",
+            }),
+        );
+        let client = mock.client();
+
+        let inbox = client
+            .send_request(&PostSubmissionsRequest::new(staff()))
+            .await
+            .unwrap()
+            .response
+            .posts;
+        let request = inbox[0]
+            .answer()
+            .unwrap()
+            .content(|answer| answer.paragraph(|p| p.text("Green.")))
+            .build_request();
+        assert!(request.parameters.validate().is_empty());
+        client.send_request(&request).await.unwrap();
+
+        client
+            .send_request(&PostGetRequest {
+                blog_id: staff(),
+                post_id: id.to_string(),
+            })
+            .await
+            .unwrap()
+            .response
+            .parameters
+    }
+
+    #[tokio::test]
+    async fn answers_anonymous_asks() {
+        let answered = answer_ask(None).await;
+        assert!(answered.is_anonymous_ask());
+        assert_eq!(answered.ask_layout().unwrap().0, [0]);
+        assert_eq!(
+            answered.to_plain_text(),
+            "What's your favourite colour?\n\nGreen."
+        );
+        assert!(matches!(
+            answered.state,
+            Some(crate::post::PostState::Published)
+        ));
+    }
+
+    #[tokio::test]
+    async fn answers_attributed_asks() {
+        let answered = answer_ask(Some(json!({
+            "type": "blog",
+            "url": "https://bob.tumblr.com/",
+            "blog": { "uuid": "t:bob", "name": "bob", "url": "https://bob.tumblr.com/" },
+        })))
+        .await;
+        assert!(!answered.is_anonymous_ask());
+        assert_eq!(answered.asker().unwrap().name(), Some("bob"));
+        assert_eq!(answered.ask_content().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn only_asks_can_be_answered() {
+        let mock = MockTumblr::start().await.unwrap();
+        mock.add_blog("staff");
+        let id = mock.add_post(
+            "staff",
+            json!({ "content": [{ "type": "text", "text": "Not an ask." }] }),
+        );
+        let post = mock
+            .client()
+            .send_request(&PostGetRequest {
+                blog_id: staff(),
+                post_id: id.to_string(),
+            })
+            .await
+            .unwrap()
+            .response
+            .parameters;
+        assert!(post.answer().is_none());
+    }
+}
@@ -0,0 +1,461 @@
+use chrono::{DateTime, Utc};
+use reqwest::Url;
+
+use crate::{
+    blog::{BlogMention, TumblrBlogId},
+    requests::{blog::posts::PostCreateRequest, media::MediaAttachment},
+};
+
+use super::{
+    tumblr_len, ContentSubtype, Formatting, MediaObject, PostContent, PostCreate, PostGet,
+    PostInteractability, PostState, ReblogInfo, Tags,
+};
+
+/// A style applied to a run of text in a [`TextBuilder`].
+#[derive(Debug, Clone)]
+pub enum TextStyle {
+    Bold,
+    Italic,
+    Strikethrough,
+    Small,
+    Link(Url),
+    Mention(BlogMention),
+    Color(String),
+}
+
+impl TextStyle {
+    pub(super) fn into_formatting(self, start: u32, end: u32) -> Formatting {
+        match self {
+            Self::Bold => Formatting::Bold { start, end },
+            Self::Italic => Formatting::Italic { start, end },
+            Self::Strikethrough => Formatting::Strikethrough { start, end },
+            Self::Small => Formatting::Small { start, end },
+            Self::Link(url) => Formatting::Link { start, end, url },
+            Self::Mention(blog) => Formatting::Mention { start, end, blog },
+            Self::Color(hex) => Formatting::Color { start, end, hex },
+        }
+    }
+}
+
+/// Builds the text and formatting of a single text block.
+///
+/// Formatting ranges are calculated from the runs of text as they're appended.
+#[derive(Debug, Default)]
+pub struct TextBuilder {
+    text: String,
+    formatting: Vec<Formatting>,
+}
+
+impl TextBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn text(mut self, text: &str) -> Self {
+        self.text.push_str(text);
+        self
+    }
+
+    /// Appends `text` with every style in `styles` applied to it.
+    pub fn styled(mut self, text: &str, styles: impl IntoIterator<Item = TextStyle>) -> Self {
+        let start = tumblr_len(&self.text);
+        self.text.push_str(text);
+        let end = tumblr_len(&self.text);
+
+        if start != end {
+            self.formatting.extend(
+                styles
+                    .into_iter()
+                    .map(|style| style.into_formatting(start, end)),
+            );
+        }
+        self
+    }
+
+    pub fn bold(self, text: &str) -> Self {
+        self.styled(text, [TextStyle::Bold])
+    }
+
+    pub fn italic(self, text: &str) -> Self {
+        self.styled(text, [TextStyle::Italic])
+    }
+
+    pub fn strikethrough(self, text: &str) -> Self {
+        self.styled(text, [TextStyle::Strikethrough])
+    }
+
+    pub fn small(self, text: &str) -> Self {
+        self.styled(text, [TextStyle::Small])
+    }
+
+    pub fn link(self, text: &str, url: Url) -> Self {
+        self.styled(text, [TextStyle::Link(url)])
+    }
+
+    pub fn mention(self, text: &str, blog: BlogMention) -> Self {
+        self.styled(text, [TextStyle::Mention(blog)])
+    }
+
+    /// `hex` is a color such as `#ff0000`.
+    pub fn color(self, text: &str, hex: impl Into<String>) -> Self {
+        self.styled(text, [TextStyle::Color(hex.into())])
+    }
+
+    fn into_content(
+        self,
+        subtype: Option<ContentSubtype>,
+        indent_level: Option<u8>,
+    ) -> PostContent {
+        PostContent::Text {
+            text: self.text,
+            subtype,
+            indent_level,
+            formatting: (!self.formatting.is_empty()).then_some(self.formatting),
+        }
+    }
+}
+
+/// Composes a [`PostCreate`] block by block.
+///
+/// ```
+/// # use tumblr_api::{post::PostBuilder, tags};
+/// let post = PostBuilder::new()
+///     .heading("Hello")
+///     .paragraph(|p| p.text("Some ").bold("bold").text(" text."))
+///     .tags(tags!["tumblr api"].unwrap())
+///     .draft()
+///     .build();
+/// ```
+#[derive(Debug, Default)]
+pub struct PostBuilder {
+    post: PostCreate,
+    media: Vec<MediaAttachment>,
+}
+
+impl PostBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn content(mut self, content: PostContent) -> Self {
+        self.post.content.push(content);
+        self
+    }
+
+    /// Adds a text block with any subtype.
+    pub fn text_block(
+        self,
+        subtype: Option<ContentSubtype>,
+        text: impl FnOnce(TextBuilder) -> TextBuilder,
+    ) -> Self {
+        self.content(text(TextBuilder::new()).into_content(subtype, None))
+    }
+
+    pub fn paragraph(self, text: impl FnOnce(TextBuilder) -> TextBuilder) -> Self {
+        self.text_block(None, text)
+    }
+
+    pub fn heading(self, text: &str) -> Self {
+        self.text_block(Some(ContentSubtype::HeadingOne), |p| p.text(text))
+    }
+
+    pub fn subheading(self, text: &str) -> Self {
+        self.text_block(Some(ContentSubtype::HeadingTwo), |p| p.text(text))
+    }
+
+    pub fn quote(self, text: impl FnOnce(TextBuilder) -> TextBuilder) -> Self {
+        self.text_block(Some(ContentSubtype::Quote), text)
+    }
+
+    pub fn quirky(self, text: impl FnOnce(TextBuilder) -> TextBuilder) -> Self {
+        self.text_block(Some(ContentSubtype::Quirky), text)
+    }
+
+    pub fn chat(self, text: impl FnOnce(TextBuilder) -> TextBuilder) -> Self {
+        self.text_block(Some(ContentSubtype::Chat), text)
+    }
+
+    /// Adds a block quote style text block, nested by `indent_level`.
+    pub fn indented(self, indent_level: u8, text: impl FnOnce(TextBuilder) -> TextBuilder) -> Self {
+        self.list_like(ContentSubtype::Indented, indent_level, text)
+    }
+
+    pub fn ordered_item(
+        self,
+        indent_level: u8,
+        text: impl FnOnce(TextBuilder) -> TextBuilder,
+    ) -> Self {
+        self.list_like(ContentSubtype::OrderedListItem, indent_level, text)
+    }
+
+    pub fn unordered_item(
+        self,
+        indent_level: u8,
+        text: impl FnOnce(TextBuilder) -> TextBuilder,
+    ) -> Self {
+        self.list_like(ContentSubtype::UnorderedListItem, indent_level, text)
+    }
+
+    fn list_like(
+        self,
+        subtype: ContentSubtype,
+        indent_level: u8,
+        text: impl FnOnce(TextBuilder) -> TextBuilder,
+    ) -> Self {
+        let indent_level = (indent_level > 0).then_some(indent_level);
+        self.content(text(TextBuilder::new()).into_content(Some(subtype), indent_level))
+    }
+
+    /// Adds an image hosted elsewhere.
+    pub fn image(self, url: Url, alt_text: Option<String>) -> Self {
+        self.content(PostContent::Image {
+            media: vec![MediaObject::from_url(url)],
+            alt_text,
+            caption: None,
+        })
+    }
+
+    /// Adds an image uploaded with the post.
+    ///
+    /// The attachment is only sent when using [`PostBuilder::build_request`].
+    pub fn image_upload(mut self, attachment: MediaAttachment, alt_text: Option<String>) -> Self {
+        let media = MediaObject::from_identifier(attachment.identifier.clone());
+        self.media.push(attachment);
+        self.content(PostContent::Image {
+            media: vec![media],
+            alt_text,
+            caption: None,
+        })
+    }
+
+    pub fn tags(mut self, tags: Tags) -> Self {
+        self.post.tags = Some(tags);
+        self
+    }
+
+    pub fn state(mut self, state: PostState) -> Self {
+        self.post.state = Some(state);
+        self
+    }
+
+    pub fn draft(self) -> Self {
+        self.state(PostState::Draft)
+    }
+
+    pub fn queue(self) -> Self {
+        self.state(PostState::Queue)
+    }
+
+    /// Queues the post to be published at `publish_on`.
+    pub fn publish_on(mut self, publish_on: DateTime<Utc>) -> Self {
+        self.post.publish_on = Some(publish_on);
+        self.queue()
+    }
+
+    /// Backdates the post.
+    pub fn date(mut self, date: DateTime<Utc>) -> Self {
+        self.post.date = Some(date);
+        self
+    }
+
+    pub fn private(self) -> Self {
+        self.state(PostState::Private)
+    }
+
+    pub fn source_url(mut self, source_url: Url) -> Self {
+        self.post.source_url = Some(source_url);
+        self
+    }
+
+    pub fn slug(mut self, slug: impl Into<String>) -> Self {
+        self.post.slug = Some(slug.into());
+        self
+    }
+
+    pub fn interactability_reblog(mut self, interactability: PostInteractability) -> Self {
+        self.post.interactability_reblog = Some(interactability);
+        self
+    }
+
+    /// Any uploaded media is discarded, use [`PostBuilder::build_request`] to keep it.
+    pub fn build(self) -> PostCreate {
+        self.post
+    }
+
+    pub fn build_request(self, blog_id: TumblrBlogId) -> PostCreateRequest {
+        PostCreateRequest {
+            blog_id,
+            parameters: self.post,
+            media: self.media,
+        }
+    }
+}
+
+/// Composes a reblog of an existing post, with an optional comment of its own.
+///
+/// Usually made from a fetched post with [`PostGet::reblog`].
+///
+/// ```no_run
+/// # use tumblr_api::{blog::TumblrBlogId, post::PostGet, tags};
+/// # fn reblog(post: &PostGet) -> Result<(), Box<dyn std::error::Error>> {
+/// let request = post
+///     .reblog()
+///     .comment(|comment| comment.paragraph(|p| p.text("Look at this!")))
+///     .tags(tags!["reblogs"]?)
+///     .build_request(TumblrBlogId::BlogName("my-blog".to_string()));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct ReblogBuilder {
+    post: PostBuilder,
+    reblog_info: ReblogInfo,
+}
+
+impl ReblogBuilder {
+    pub fn new(reblog_info: ReblogInfo) -> Self {
+        Self {
+            post: PostBuilder::new(),
+            reblog_info,
+        }
+    }
+
+    /// Adds to the reblog's own content, which appears below the trail.
+    ///
+    /// Any other setting of the post, like its state, can be changed here too.
+    pub fn comment(mut self, comment: impl FnOnce(PostBuilder) -> PostBuilder) -> Self {
+        self.post = comment(self.post);
+        self
+    }
+
+    pub fn tags(self, tags: Tags) -> Self {
+        self.comment(|post| post.tags(tags))
+    }
+
+    pub fn hide_trail(mut self, hide_trail: bool) -> Self {
+        self.reblog_info.hide_trail = Some(hide_trail);
+        self
+    }
+
+    /// Leaves out trail items by their index in [`PostGet::trail`].
+    pub fn exclude_trail_items(mut self, items: impl IntoIterator<Item = u16>) -> Self {
+        self.reblog_info.exclude_trail_items = Some(items.into_iter().collect());
+        self
+    }
+
+    /// Any uploaded media is discarded, use [`ReblogBuilder::build_request`] to keep it.
+    pub fn build(self) -> PostCreate {
+        PostCreate {
+            reblog_info: Some(self.reblog_info),
+            ..self.post.build()
+        }
+    }
+
+    pub fn build_request(self, blog_id: TumblrBlogId) -> PostCreateRequest {
+        let mut request = self.post.build_request(blog_id);
+        request.parameters.reblog_info = Some(self.reblog_info);
+        request
+    }
+}
+
+impl PostGet {
+    /// What's needed to reblog this post.
+    ///
+    /// A missing reblog key is left empty, which [`PostCreate::validate`] reports.
+    pub fn reblog_info(&self) -> ReblogInfo {
+        ReblogInfo {
+            parent_tumblelog_uuid: self.tumblelog_uuid.clone(),
+            parent_post_id: self.id,
+            reblog_key: self.reblog_key.clone().unwrap_or_default(),
+            ..Default::default()
+        }
+    }
+
+    pub fn reblog(&self) -> ReblogBuilder {
+        ReblogBuilder::new(self.reblog_info())
+    }
+}
+
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+    use serde_json::json;
+
+    use crate::{
+        blog::TumblrBlogId,
+        post::{PostGet, ValidationIssue},
+        requests::blog::posts::PostGetRequest,
+        testing::MockTumblr,
+        TumblrClient,
+    };
+
+    fn blog(name: &str) -> TumblrBlogId {
+        TumblrBlogId::BlogName(name.to_string())
+    }
+
+    async fn fetch(client: &TumblrClient, blog_name: &str, id: u64) -> PostGet {
+        client
+            .send_request(&PostGetRequest {
+                blog_id: blog(blog_name),
+                post_id: id.to_string(),
+            })
+            .await
+            .unwrap()
+            .response
+            .parameters
+    }
+
+    async fn fetched_post() -> (MockTumblr, PostGet) {
+        let mock = MockTumblr::start().await.unwrap();
+        mock.add_blog("staff");
+        mock.add_blog("fan");
+        let id = mock.add_post(
+            "staff",
+            json!({ "content": [{ "type": "text", "text": "Original." }] }),
+        );
+        let post = fetch(&mock.client(), "staff", id).await;
+        (mock, post)
+    }
+
+    #[tokio::test]
+    async fn fetched_posts_fill_in_the_reblog_info() {
+        let (mock, post) = fetched_post().await;
+        let staff = mock.posts("staff")[0]["tumblelog_uuid"].clone();
+
+        let info = post.reblog_info();
+        assert_eq!(info.parent_tumblelog_uuid, staff);
+        assert_eq!(info.parent_post_id, post.id);
+        assert_eq!(info.reblog_key, format!("mock{}", post.id));
+        assert_eq!((info.hide_trail, info.exclude_trail_items), (None, None));
+
+        let request = post
+            .reblog()
+            .comment(|comment| comment.paragraph(|p| p.text("Look at this!")))
+            .hide_trail(false)
+            .exclude_trail_items([0])
+            .build_request(blog("fan"));
+        assert!(request.parameters.validate().is_empty());
+
+        let client = mock.client();
+        let id = client.send_request(&request).await.unwrap().response.id;
+        let sent = mock.requests().pop().unwrap().json().unwrap();
+        assert_eq!(sent["parent_tumblelog_uuid"], staff);
+        assert_eq!(sent["parent_post_id"], post.id);
+        assert_eq!(sent["reblog_key"], format!("mock{}", post.id));
+        assert_eq!(sent["exclude_trail_items"], json!([0]));
+
+        let reblog = fetch(&client, "fan", id.parse().unwrap()).await;
+        assert_eq!(reblog.to_plain_text(), "Look at this!");
+    }
+
+    #[tokio::test]
+    async fn reblogs_need_a_reblog_key() {
+        let (_mock, mut post) = fetched_post().await;
+        post.reblog_key = None;
+        assert_eq!(
+            post.reblog().build().validate(),
+            [ValidationIssue::IncompleteReblogInfo {
+                field: "reblog_key"
+            }]
+        );
+    }
+}
@@ -4,7 +4,9 @@ use serde_with::skip_serializing_none;
 
 use crate::blog::BlogMention;
 
-#[derive(Debug, Serialize, Deserialize)]
+use super::TextOffsets;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "kebab-case")]
 pub enum Formatting {
     // Basic
@@ -43,8 +45,133 @@ pub enum Formatting {
     },
 }
 
+impl Formatting {
+    pub fn start(&self) -> u32 {
+        match self {
+            Self::Bold { start, .. }
+            | Self::Italic { start, .. }
+            | Self::Strikethrough { start, .. }
+            | Self::Small { start, .. }
+            | Self::Link { start, .. }
+            | Self::Mention { start, .. }
+            | Self::Color { start, .. } => *start,
+        }
+    }
+
+    pub fn end(&self) -> u32 {
+        match self {
+            Self::Bold { end, .. }
+            | Self::Italic { end, .. }
+            | Self::Strikethrough { end, .. }
+            | Self::Small { end, .. }
+            | Self::Link { end, .. }
+            | Self::Mention { end, .. }
+            | Self::Color { end, .. } => *end,
+        }
+    }
+
+    fn range_mut(&mut self) -> (&mut u32, &mut u32) {
+        match self {
+            Self::Bold { start, end }
+            | Self::Italic { start, end }
+            | Self::Strikethrough { start, end }
+            | Self::Small { start, end }
+            | Self::Link { start, end, .. }
+            | Self::Mention { start, end, .. }
+            | Self::Color { start, end, .. } => (start, end),
+        }
+    }
+
+    /// Limits the range to text `len` characters long.
+    pub fn clamp(&mut self, len: u32) {
+        let (start, end) = self.range_mut();
+        *start = (*start).min(len);
+        *end = (*end).min(len);
+    }
+}
+
+/// A piece of formatted text, as produced by [`formatted_spans`].
+#[derive(Debug)]
+pub(crate) enum FormattedSpan<'a> {
+    Open(&'a Formatting),
+    Close(&'a Formatting),
+    Text(&'a str),
+}
+
+/// Walks `text` with its formatting applied, splitting overlapping ranges so they nest.
+///
+/// Ranges are clamped to the text, and empty or inverted ranges are skipped.
+pub(crate) fn formatted_spans<'a>(
+    text: &'a str,
+    formatting: &'a [Formatting],
+) -> Vec<FormattedSpan<'a>> {
+    let offsets = TextOffsets::new(text);
+    let len = offsets.len();
+
+    let ranges = formatting
+        .iter()
+        .map(|formatting| {
+            let start = formatting.start().min(len);
+            let end = formatting.end().min(len);
+            (start, end)
+        })
+        .collect::<Vec<_>>();
+
+    let mut points = ranges
+        .iter()
+        .flat_map(|(start, end)| [*start, *end])
+        .chain([0, len])
+        .collect::<Vec<_>>();
+    points.sort_unstable();
+    points.dedup();
+    let bytes = offsets
+        .byte_indices(&points)
+        .into_iter()
+        .map(|byte| byte.unwrap_or(text.len()))
+        .collect::<Vec<_>>();
+
+    let mut spans = Vec::new();
+    // Indices into `formatting` of the currently open ranges, innermost last.
+    let mut open: Vec<usize> = Vec::new();
+
+    for (window, bytes) in points.windows(2).zip(bytes.windows(2)) {
+        let position = window[0];
+
+        // Close everything down to the outermost range that ends here, reopening the rest.
+        let mut reopen = Vec::new();
+        if let Some(depth) = open.iter().position(|index| ranges[*index].1 <= position) {
+            for index in open.drain(depth..).rev() {
+                spans.push(FormattedSpan::Close(&formatting[index]));
+                if ranges[index].1 > position {
+                    reopen.push(index);
+                }
+            }
+        }
+
+        reopen.extend(
+            (0..formatting.len())
+                .filter(|index| ranges[*index].0 == position && ranges[*index].1 > position),
+        );
+        // Longer ranges go on the outside so they need splitting less often.
+        reopen.sort_by_key(|index| (std::cmp::Reverse(ranges[*index].1), *index));
+        for index in reopen {
+            spans.push(FormattedSpan::Open(&formatting[index]));
+            open.push(index);
+        }
+
+        spans.push(FormattedSpan::Text(&text[bytes[0]..bytes[1]]));
+    }
+
+    spans.extend(
+        open.into_iter()
+            .rev()
+            .map(|index| FormattedSpan::Close(&formatting[index])),
+    );
+    spans
+}
+
 /// https://www.tumblr.com/docs/npf#text-block-basic-subtypes
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub enum ContentSubtype {
     #[serde(rename = "heading1")]
@@ -59,8 +186,49 @@ pub enum ContentSubtype {
     UnorderedListItem,
 }
 
+impl ContentSubtype {
+    pub fn is_list_item(&self) -> bool {
+        matches!(self, Self::OrderedListItem | Self::UnorderedListItem)
+    }
+}
+
+/// https://www.tumblr.com/docs/npf#media-objects
 #[skip_serializing_none]
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct MediaObject {
+    pub url: Option<Url>,
+    #[serde(rename = "type")]
+    pub mime_type: Option<String>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub original_dimensions_missing: Option<bool>,
+    pub cropped: Option<bool>,
+    pub has_original_dimensions: Option<bool>,
+    /// Name of the multipart form field holding an uploaded file.
+    ///
+    /// Only used when creating or editing a post.
+    pub identifier: Option<String>,
+}
+
+impl MediaObject {
+    /// References a file attached to the post request under `identifier`.
+    pub fn from_identifier(identifier: impl Into<String>) -> Self {
+        Self {
+            identifier: Some(identifier.into()),
+            ..Default::default()
+        }
+    }
+
+    pub fn from_url(url: Url) -> Self {
+        Self {
+            url: Some(url),
+            ..Default::default()
+        }
+    }
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "kebab-case")]
 pub enum PostContent {
     Text {
@@ -69,9 +237,86 @@ pub enum PostContent {
         indent_level: Option<u8>,
         formatting: Option<Vec<Formatting>>,
     },
-    Image,
+    /// https://www.tumblr.com/docs/npf#content-block-type-image
+    Image {
+        media: Vec<MediaObject>,
+        alt_text: Option<String>,
+        caption: Option<String>,
+    },
     Link,
-    Audio,
-    Video,
+    /// https://www.tumblr.com/docs/npf#content-block-type-audio
+    Audio {
+        url: Option<Url>,
+        media: Option<MediaObject>,
+        provider: Option<String>,
+        title: Option<String>,
+        artist: Option<String>,
+        album: Option<String>,
+        poster: Option<Vec<MediaObject>>,
+        embed_html: Option<String>,
+        embed_url: Option<Url>,
+    },
+    /// https://www.tumblr.com/docs/npf#content-block-type-video
+    Video {
+        url: Option<Url>,
+        media: Option<MediaObject>,
+        provider: Option<String>,
+        embed_html: Option<String>,
+        embed_url: Option<Url>,
+        poster: Option<Vec<MediaObject>>,
+    },
     Paywall,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Spans written out with `[b`/`b]` style markers around each range.
+    fn spans(text: &str, formatting: &[Formatting]) -> String {
+        let marker = |formatting: &Formatting| match formatting {
+            Formatting::Bold { .. } => "b",
+            Formatting::Italic { .. } => "i",
+            _ => "?",
+        };
+        formatted_spans(text, formatting)
+            .into_iter()
+            .map(|span| match span {
+                FormattedSpan::Open(formatting) => format!("[{}", marker(formatting)),
+                FormattedSpan::Close(formatting) => format!("{}]", marker(formatting)),
+                FormattedSpan::Text(text) => text.to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn splits_overlapping_ranges() {
+        let formatting = [
+            Formatting::Bold { start: 0, end: 4 },
+            Formatting::Italic { start: 2, end: 6 },
+        ];
+        assert_eq!(spans("abcdef", &formatting), "[bab[icdi]b][iefi]");
+    }
+
+    #[test]
+    fn nests_longer_ranges_outside() {
+        let formatting = [
+            Formatting::Italic { start: 1, end: 2 },
+            Formatting::Bold { start: 0, end: 3 },
+        ];
+        assert_eq!(spans("abc", &formatting), "[ba[ibi]cb]");
+    }
+
+    #[test]
+    fn counts_code_points() {
+        let formatting = [
+            Formatting::Bold { start: 1, end: 3 },
+            Formatting::Italic { start: 2, end: 10 },
+        ];
+        assert_eq!(
+            spans("a\u{1F44B}e\u{301}z", &formatting),
+            // The combining accent is a code point of its own.
+            "a[b\u{1F44B}[iei]b][i\u{301}zi]"
+        );
+    }
+}
@@ -0,0 +1,131 @@
+use chrono::{DateTime, NaiveDateTime, SecondsFormat, Utc};
+use serde::{de, Deserialize, Deserializer, Serializer};
+
+const TUMBLR_FORMAT: &str = "%Y-%m-%d %H:%M:%S GMT";
+
+/// Parses either Tumblr's own format, such as `2023-07-21 19:05:07 GMT`, or RFC 3339.
+pub fn parse_tumblr_date(date: &str) -> Option<DateTime<Utc>> {
+    NaiveDateTime::parse_from_str(date, TUMBLR_FORMAT)
+        .map(|date| date.and_utc())
+        .or_else(|_| DateTime::parse_from_rfc3339(date).map(|date| date.with_timezone(&Utc)))
+        .ok()
+}
+
+/// Formats a date as ISO 8601, which is what Tumblr expects when posting.
+pub fn format_tumblr_date(date: &DateTime<Utc>) -> String {
+    date.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+pub(crate) mod tumblr_date_option {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        date: &Option<DateTime<Utc>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match date {
+            Some(date) => serializer.serialize_str(&format_tumblr_date(date)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    /// An empty string is read as no date.
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<DateTime<Utc>>, D::Error> {
+        match Option::<String>::deserialize(deserializer)? {
+            Some(date) if !date.is_empty() => parse_tumblr_date(&date)
+                .map(Some)
+                .ok_or_else(|| de::Error::custom(format!("invalid Tumblr date '{date}'"))),
+            _ => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use serde::Serialize;
+    use serde_json::json;
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Dated {
+        #[serde(default, with = "tumblr_date_option")]
+        date: Option<DateTime<Utc>>,
+        #[serde(default, with = "tumblr_date_option")]
+        publish_on: Option<DateTime<Utc>>,
+    }
+
+    fn date() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2023, 7, 21, 19, 5, 7).unwrap()
+    }
+
+    #[test]
+    fn parses_tumblr_and_rfc_3339_dates() {
+        assert_eq!(parse_tumblr_date("2023-07-21 19:05:07 GMT"), Some(date()));
+        assert_eq!(parse_tumblr_date("2023-07-21T19:05:07Z"), Some(date()));
+        assert_eq!(parse_tumblr_date("2023-07-21T21:05:07+02:00"), Some(date()));
+        assert_eq!(parse_tumblr_date("2023-07-21 19:05:07"), None);
+        assert_eq!(parse_tumblr_date("yesterday"), None);
+    }
+
+    #[test]
+    fn formats_as_rfc_3339() {
+        assert_eq!(format_tumblr_date(&date()), "2023-07-21T19:05:07Z");
+    }
+
+    #[test]
+    fn round_trips_dates() {
+        let dated: Dated = serde_json::from_value(json!({
+            "date": "2023-07-21 19:05:07 GMT",
+            "publish_on": "2023-07-21T19:05:07Z",
+        }))
+        .unwrap();
+        assert_eq!(
+            dated,
+            Dated {
+                date: Some(date()),
+                publish_on: Some(date()),
+            }
+        );
+        assert_eq!(
+            serde_json::to_value(&dated).unwrap(),
+            json!({ "date": "2023-07-21T19:05:07Z", "publish_on": "2023-07-21T19:05:07Z" })
+        );
+    }
+
+    #[test]
+    fn missing_optional_dates_are_none() {
+        for publish_on in [json!(""), json!(null)] {
+            let dated: Dated = serde_json::from_value(json!({
+                "date": "2023-07-21 19:05:07 GMT",
+                "publish_on": publish_on,
+            }))
+            .unwrap();
+            assert_eq!(dated.publish_on, None);
+        }
+
+        let dated: Dated =
+            serde_json::from_value(json!({ "date": "2023-07-21 19:05:07 GMT" })).unwrap();
+        assert_eq!(dated.publish_on, None);
+        assert_eq!(
+            serde_json::to_value(&dated).unwrap()["publish_on"],
+            json!(null)
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_dates() {
+        let error = serde_json::from_value::<Dated>(json!({ "date": "yesterday" })).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("invalid Tumblr date 'yesterday'"));
+        assert!(serde_json::from_value::<Dated>(json!({
+            "date": "2023-07-21 19:05:07 GMT",
+            "publish_on": "tomorrow",
+        }))
+        .is_err());
+    }
+}
@@ -0,0 +1,543 @@
+#[cfg(feature = "html")]
+mod import;
+
+#[cfg(feature = "html")]
+pub use self::import::*;
+
+use std::fmt::Write;
+
+use reqwest::Url;
+
+use super::{
+    formatted_spans,
+    validate::{is_hex_color, is_safe_url},
+    ContentSubtype, DisplayRows, FormattedSpan, Formatting, MediaObject, PostContent, PostGet,
+    PostLayout, PostTrail,
+};
+
+/// Renders NPF content into semantic HTML.
+///
+/// All text and attributes are escaped. Embeds provided by Tumblr as raw HTML are never
+/// included, they're linked to instead. Only `http`, `https` and `mailto` URLs become links or
+/// media sources, and colors that aren't hex codes are left out.
+#[derive(Debug, Default, Clone)]
+pub struct HtmlRenderer {
+    truncate: bool,
+    read_more_url: Option<Url>,
+}
+
+impl HtmlRenderer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stops rendering at the "keep reading" fold, optionally linking to the full post.
+    pub fn truncate(mut self, read_more_url: Option<Url>) -> Self {
+        self.truncate = true;
+        self.read_more_url = read_more_url;
+        self
+    }
+
+    /// Renders the reblog trail followed by the post's own content.
+    pub fn render_post(&self, post: &PostGet) -> String {
+        let mut html = String::from("<article class=\"npf-post\">");
+        for trail in &post.trail {
+            html.push_str(&self.render_trail(trail));
+        }
+        html.push_str(&self.render(&post.content, &post.layout));
+        html.push_str("</article>");
+        html
+    }
+
+    /// Renders a trail item under a header naming its blog.
+    pub fn render_trail(&self, trail: &PostTrail) -> String {
+        let header = match (&trail.blog, trail.blog_name()) {
+            (Some(blog), _) => {
+                let mention = blog.mention();
+                let name = escape_html(&blog.name);
+                match mention.url().filter(is_safe_url) {
+                    Some(url) => format!(
+                        "<header class=\"npf-trail-blog\"><a href=\"{}\">{name}</a></header>",
+                        escape_html(url.as_str())
+                    ),
+                    None => format!("<header class=\"npf-trail-blog\">{name}</header>"),
+                }
+            }
+            (None, Some(name)) => format!(
+                "<header class=\"npf-trail-blog npf-trail-blog-broken\">{}</header>",
+                escape_html(name)
+            ),
+            (None, None) => String::new(),
+        };
+        format!(
+            "<section class=\"npf-trail-item\">{header}{}</section>",
+            self.render(&trail.content, &trail.layout)
+        )
+    }
+
+    pub fn render(&self, content: &[PostContent], layout: &[PostLayout]) -> String {
+        let display = DisplayRows::new(layout, content.len());
+        let mut html = String::new();
+        let mut lists = ListState::default();
+
+        for (index, row) in display.rows.iter().enumerate() {
+            if let [block] = row.as_slice() {
+                render_flow_block(&mut html, &mut lists, &content[*block]);
+            } else {
+                lists.close_all(&mut html);
+                html.push_str("<div class=\"npf-row\">");
+                let mut row_lists = ListState::default();
+                for block in row {
+                    render_flow_block(&mut html, &mut row_lists, &content[*block]);
+                }
+                row_lists.close_all(&mut html);
+                html.push_str("</div>");
+            }
+
+            if self.truncate && display.truncate_after == Some(index) {
+                lists.close_all(&mut html);
+                if let Some(url) = self.read_more_url.as_ref().filter(|url| is_safe_url(url)) {
+                    let _ = write!(
+                        html,
+                        "<p class=\"npf-read-more\"><a href=\"{}\">Keep reading</a></p>",
+                        escape_html(url.as_str())
+                    );
+                }
+                return html;
+            }
+        }
+
+        lists.close_all(&mut html);
+        html
+    }
+}
+
+/// Open `<ol>`/`<ul>` elements, innermost last.
+#[derive(Debug, Default)]
+struct ListState {
+    /// Whether each open list is ordered.
+    open: Vec<bool>,
+}
+
+impl ListState {
+    fn close_one(&mut self, html: &mut String) {
+        if let Some(ordered) = self.open.pop() {
+            html.push_str(if ordered { "</li></ol>" } else { "</li></ul>" });
+        }
+    }
+
+    fn close_all(&mut self, html: &mut String) {
+        while !self.open.is_empty() {
+            self.close_one(html);
+        }
+    }
+
+    /// Opens a `<li>` at `indent_level`, closing or opening lists around it as needed.
+    fn open_item(&mut self, html: &mut String, ordered: bool, indent_level: usize) {
+        let depth = indent_level + 1;
+        while self.open.len() > depth {
+            self.close_one(html);
+        }
+        if self.open.len() == depth && self.open.last() != Some(&ordered) {
+            self.close_one(html);
+        }
+
+        if self.open.len() == depth {
+            html.push_str("</li>");
+        }
+        while self.open.len() < depth {
+            html.push_str(if ordered { "<ol>" } else { "<ul>" });
+            self.open.push(ordered);
+            // Skipped levels still need an item to nest inside.
+            if self.open.len() < depth {
+                html.push_str("<li>");
+            }
+        }
+        html.push_str("<li>");
+    }
+}
+
+fn render_flow_block(html: &mut String, lists: &mut ListState, block: &PostContent) {
+    match block {
+        PostContent::Text {
+            text,
+            subtype: Some(subtype),
+            indent_level,
+            formatting,
+        } if subtype.is_list_item() => {
+            lists.open_item(
+                html,
+                *subtype == ContentSubtype::OrderedListItem,
+                indent_level.unwrap_or_default() as usize,
+            );
+            html.push_str(&render_html_text(
+                text,
+                formatting.as_deref().unwrap_or_default(),
+            ));
+        }
+        _ => {
+            lists.close_all(html);
+            render_block(html, block);
+        }
+    }
+}
+
+fn render_block(html: &mut String, block: &PostContent) {
+    match block {
+        PostContent::Text {
+            text,
+            subtype,
+            indent_level,
+            formatting,
+        } => {
+            let inner = render_html_text(text, formatting.as_deref().unwrap_or_default());
+            let _ = match subtype {
+                None => write!(html, "<p>{inner}</p>"),
+                Some(ContentSubtype::HeadingOne) => write!(html, "<h1>{inner}</h1>"),
+                Some(ContentSubtype::HeadingTwo) => write!(html, "<h2>{inner}</h2>"),
+                Some(ContentSubtype::Quirky) => write!(html, "<p class=\"npf-quirky\">{inner}</p>"),
+                Some(ContentSubtype::Quote) => {
+                    write!(
+                        html,
+                        "<blockquote class=\"npf-quote\"><p>{inner}</p></blockquote>"
+                    )
+                }
+                Some(ContentSubtype::Chat) => write!(html, "<p class=\"npf-chat\">{inner}</p>"),
+                Some(ContentSubtype::Indented) => {
+                    let depth = indent_level.unwrap_or_default() as usize + 1;
+                    write!(
+                        html,
+                        "{}<p>{inner}</p>{}",
+                        "<blockquote class=\"npf-indented\">".repeat(depth),
+                        "</blockquote>".repeat(depth)
+                    )
+                }
+                // Only reachable for list items rendered outside of a list flow.
+                Some(ContentSubtype::OrderedListItem) => write!(html, "<ol><li>{inner}</li></ol>"),
+                Some(ContentSubtype::UnorderedListItem) => {
+                    write!(html, "<ul><li>{inner}</li></ul>")
+                }
+            };
+        }
+        PostContent::Image {
+            media,
+            alt_text,
+            caption,
+        } => {
+            let Some(url) = media.first().and_then(media_url) else {
+                return;
+            };
+            html.push_str("<figure class=\"npf-image\">");
+            let _ = write!(
+                html,
+                "<img src=\"{}\" alt=\"{}\"",
+                escape_html(url.as_str()),
+                escape_html(alt_text.as_deref().unwrap_or_default())
+            );
+            if let Some(srcset) = srcset(media) {
+                let _ = write!(html, " srcset=\"{}\"", escape_html(&srcset));
+            }
+            html.push('>');
+            if let Some(caption) = caption {
+                let _ = write!(html, "<figcaption>{}</figcaption>", escape_html(caption));
+            }
+            html.push_str("</figure>");
+        }
+        PostContent::Video {
+            url, media, poster, ..
+        } => {
+            if let Some(src) = media.as_ref().and_then(media_url) {
+                html.push_str("<video class=\"npf-video\" controls");
+                if let Some(poster) = poster
+                    .as_ref()
+                    .and_then(|poster| poster.first())
+                    .and_then(media_url)
+                {
+                    let _ = write!(html, " poster=\"{}\"", escape_html(poster.as_str()));
+                }
+                let _ = write!(html, " src=\"{}\"></video>", escape_html(src.as_str()));
+            } else if let Some(url) = url {
+                render_embed_link(html, "npf-video", url, None);
+            }
+        }
+        PostContent::Audio {
+            url,
+            media,
+            title,
+            artist,
+            ..
+        } => {
+            let label = match (title, artist) {
+                (Some(title), Some(artist)) => Some(format!("{artist} - {title}")),
+                (Some(title), None) => Some(title.clone()),
+                _ => None,
+            };
+            if let Some(src) = media.as_ref().and_then(media_url) {
+                html.push_str("<figure class=\"npf-audio\">");
+                let _ = write!(
+                    html,
+                    "<audio controls src=\"{}\"></audio>",
+                    escape_html(src.as_str())
+                );
+                if let Some(label) = label {
+                    let _ = write!(html, "<figcaption>{}</figcaption>", escape_html(&label));
+                }
+                html.push_str("</figure>");
+            } else if let Some(url) = url {
+                render_embed_link(html, "npf-audio", url, label.as_deref());
+            }
+        }
+        PostContent::Link | PostContent::Paywall => (),
+    }
+}
+
+fn render_embed_link(html: &mut String, class: &str, url: &Url, label: Option<&str>) {
+    let label = escape_html(label.unwrap_or(url.as_str()));
+    let _ = if is_safe_url(url) {
+        write!(
+            html,
+            "<p class=\"{class}\"><a href=\"{}\">{label}</a></p>",
+            escape_html(url.as_str()),
+        )
+    } else {
+        write!(html, "<p class=\"{class}\">{label}</p>")
+    };
+}
+
+/// The media's URL, if it's safe to load.
+fn media_url(media: &MediaObject) -> Option<&Url> {
+    media.url.as_ref().filter(|url| is_safe_url(url))
+}
+
+fn srcset(media: &[MediaObject]) -> Option<String> {
+    let candidates = media
+        .iter()
+        .filter_map(|media| Some(format!("{} {}w", media_url(media)?, media.width?)))
+        .collect::<Vec<_>>();
+    (candidates.len() > 1).then(|| candidates.join(", "))
+}
+
+/// Links to unsafe URLs and colors that aren't hex codes render as plain text.
+pub(crate) fn has_tag(formatting: &Formatting) -> bool {
+    match formatting {
+        Formatting::Link { url, .. } => is_safe_url(url),
+        Formatting::Color { hex, .. } => is_hex_color(hex),
+        _ => true,
+    }
+}
+
+fn open_tag(formatting: &Formatting) -> String {
+    if !has_tag(formatting) {
+        return String::new();
+    }
+    match formatting {
+        Formatting::Bold { .. } => "<strong>".to_string(),
+        Formatting::Italic { .. } => "<em>".to_string(),
+        Formatting::Strikethrough { .. } => "<s>".to_string(),
+        Formatting::Small { .. } => "<small>".to_string(),
+        Formatting::Link { url, .. } => format!("<a href=\"{}\">", escape_html(url.as_str())),
+        Formatting::Mention { blog, .. } => match blog.url().filter(is_safe_url) {
+            Some(url) => format!(
+                "<a class=\"npf-mention\" href=\"{}\">",
+                escape_html(url.as_str())
+            ),
+            None => "<a class=\"npf-mention\">".to_string(),
+        },
+        Formatting::Color { hex, .. } => {
+            format!("<span style=\"color: {}\">", escape_html(hex))
+        }
+    }
+}
+
+fn close_tag(formatting: &Formatting) -> &'static str {
+    if !has_tag(formatting) {
+        return "";
+    }
+    match formatting {
+        Formatting::Bold { .. } => "</strong>",
+        Formatting::Italic { .. } => "</em>",
+        Formatting::Strikethrough { .. } => "</s>",
+        Formatting::Small { .. } => "</small>",
+        Formatting::Link { .. } | Formatting::Mention { .. } => "</a>",
+        Formatting::Color { .. } => "</span>",
+    }
+}
+
+/// Applies formatting ranges to text, splitting overlapping ranges so tags nest correctly.
+pub fn render_html_text(text: &str, formatting: &[Formatting]) -> String {
+    let mut html = String::new();
+    for span in formatted_spans(text, formatting) {
+        match span {
+            FormattedSpan::Open(formatting) => html.push_str(&open_tag(formatting)),
+            FormattedSpan::Close(formatting) => html.push_str(close_tag(formatting)),
+            FormattedSpan::Text(text) => html.push_str(&escape_html(text).replace('\n', "<br>")),
+        }
+    }
+    html
+}
+
+/// Escapes text for use in HTML content and quoted attributes.
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for character in text.chars() {
+        match character {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(character),
+        }
+    }
+    escaped
+}
+
+impl PostGet {
+    pub fn to_html(&self) -> String {
+        HtmlRenderer::new().render_post(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn link(url: &str) -> Formatting {
+        Formatting::Link {
+            start: 0,
+            end: 4,
+            url: url.parse().unwrap(),
+        }
+    }
+
+    #[test]
+    fn renders_safe_links() {
+        assert_eq!(
+            render_html_text("link", &[link("https://example.com/")]),
+            "<a href=\"https://example.com/\">link</a>"
+        );
+        assert_eq!(
+            render_html_text("mail", &[link("mailto:staff@example.com")]),
+            "<a href=\"mailto:staff@example.com\">mail</a>"
+        );
+    }
+
+    #[test]
+    fn drops_unsafe_links() {
+        assert_eq!(
+            render_html_text("link", &[link("javascript:alert(1)")]),
+            "link"
+        );
+        assert_eq!(
+            render_html_text("link", &[link("data:text/html,<script>")]),
+            "link"
+        );
+    }
+
+    #[test]
+    fn drops_unsafe_colors() {
+        let color = |hex: &str| Formatting::Color {
+            start: 0,
+            end: 3,
+            hex: hex.to_string(),
+        };
+        assert_eq!(
+            render_html_text("red", &[color("#ff0000")]),
+            "<span style=\"color: #ff0000\">red</span>"
+        );
+        assert_eq!(
+            render_html_text(
+                "red",
+                &[color("red;background:url(https://evil.example/x)")]
+            ),
+            "red"
+        );
+    }
+
+    #[test]
+    fn drops_unsafe_read_more_and_embed_links() {
+        let content = [
+            PostContent::Text {
+                text: "above".to_string(),
+                subtype: None,
+                indent_level: None,
+                formatting: None,
+            },
+            PostContent::Text {
+                text: "below".to_string(),
+                subtype: None,
+                indent_level: None,
+                formatting: None,
+            },
+        ];
+        let layout = [PostLayout::Rows {
+            display: Vec::new(),
+            truncate_after: Some(0),
+        }];
+        let html = HtmlRenderer::new()
+            .truncate(Some("javascript:alert(1)".parse().unwrap()))
+            .render(&content, &layout);
+        assert_eq!(html, "<p>above</p>");
+
+        let mut html = String::new();
+        render_embed_link(
+            &mut html,
+            "npf-video",
+            &"javascript:alert(1)".parse().unwrap(),
+            Some("video"),
+        );
+        assert_eq!(html, "<p class=\"npf-video\">video</p>");
+    }
+
+    #[test]
+    fn escapes_text() {
+        assert_eq!(
+            render_html_text("<b>\"&'", &[]),
+            "&lt;b&gt;&quot;&amp;&#39;"
+        );
+    }
+
+    #[test]
+    fn drops_unsafe_media_urls() {
+        let blocks: Vec<PostContent> = serde_json::from_value(serde_json::json!([
+            {
+                "type": "image",
+                "media": [
+                    { "url": "https://example.com/1280.png", "width": 1280 },
+                    { "url": "javascript:alert(1)", "width": 640 },
+                    { "url": "https://example.com/400.png", "width": 400 },
+                ],
+            },
+            { "type": "image", "media": [{ "url": "javascript:alert(1)" }] },
+            {
+                "type": "video",
+                "media": { "url": "https://example.com/video.mp4" },
+                "poster": [{ "url": "javascript:alert(1)" }],
+            },
+            { "type": "video", "media": { "url": "javascript:alert(1)" } },
+            { "type": "audio", "media": { "url": "data:audio/mp3;base64,AAAA" } },
+        ]))
+        .unwrap();
+        let rendered = blocks
+            .iter()
+            .map(|block| {
+                let mut html = String::new();
+                render_block(&mut html, block);
+                html
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            rendered,
+            [
+                "<figure class=\"npf-image\"><img src=\"https://example.com/1280.png\" alt=\"\" \
+                 srcset=\"https://example.com/1280.png 1280w, https://example.com/400.png 400w\">\
+                 </figure>",
+                "",
+                "<video class=\"npf-video\" controls src=\"https://example.com/video.mp4\"></video>",
+                "",
+                "",
+            ]
+        );
+    }
+}
@@ -0,0 +1,443 @@
+use reqwest::Url;
+use scraper::{node::Element, Html, Node};
+
+use crate::post::{
+    validate::{is_hex_color, is_safe_url},
+    writer::{BlockContext, ContentWriter, DroppedContent},
+    ContentSubtype, MediaObject, PostBody, PostContent, TextStyle,
+};
+
+use ego_tree::iter::Edge;
+
+type NodeRef<'a> = ego_tree::NodeRef<'a, Node>;
+
+/// How deep the importer follows nested elements, deeper ones are flattened to their text.
+const MAX_NESTING: usize = 128;
+/// Elements left out along with everything inside them.
+const REMOVED_ELEMENTS: [&str; 18] = [
+    "script", "style", "iframe", "object", "embed", "noscript", "template", "head", "title",
+    "meta", "link", "svg", "canvas", "form", "input", "button", "select", "textarea",
+];
+
+/// The result of [`from_html`].
+#[derive(Debug, Default)]
+pub struct HtmlImport {
+    pub body: PostBody,
+    /// Everything that was left out, each reported once.
+    pub dropped: Vec<DroppedContent>,
+}
+
+/// Converts an HTML fragment, such as a legacy post body, into NPF content blocks.
+///
+/// A `<!-- more -->` comment marks where the post is cut off by "keep reading".
+pub fn from_html(html: &str) -> HtmlImport {
+    let document = Html::parse_fragment(html);
+    let mut importer = HtmlImporter::default();
+    importer.children(document.tree.root());
+    importer.end_block();
+
+    HtmlImport {
+        body: importer.writer.finish(),
+        dropped: importer.dropped,
+    }
+}
+
+#[derive(Debug, Default)]
+struct HtmlImporter {
+    writer: ContentWriter,
+    context: BlockContext,
+    dropped: Vec<DroppedContent>,
+    /// How many elements deep the walk currently is.
+    depth: usize,
+}
+
+impl HtmlImporter {
+    fn drop_content(&mut self, dropped: DroppedContent) {
+        if !self.dropped.contains(&dropped) {
+            self.dropped.push(dropped);
+        }
+    }
+
+    /// An absolute URL that's safe to link to, anything else is reported as dropped.
+    fn url(&mut self, url: &str) -> Option<Url> {
+        match Url::parse(url) {
+            Ok(parsed) if is_safe_url(&parsed) => Some(parsed),
+            Ok(_) => {
+                self.drop_content(DroppedContent::UnsafeUrl(url.to_string()));
+                None
+            }
+            Err(_) => {
+                self.drop_content(DroppedContent::InvalidUrl(url.to_string()));
+                None
+            }
+        }
+    }
+
+    fn children(&mut self, node: NodeRef) {
+        if self.depth >= MAX_NESTING {
+            self.flattened_text(node);
+            return;
+        }
+
+        self.depth += 1;
+        for child in node.children() {
+            self.node(child);
+        }
+        self.depth -= 1;
+    }
+
+    /// Keeps the text of everything under `node`, walking it without recursion.
+    fn flattened_text(&mut self, node: NodeRef) {
+        self.drop_content(DroppedContent::NestingTooDeep);
+        // How many removed elements the walk is inside of.
+        let mut removed = 0usize;
+        for edge in node.traverse() {
+            let (node, opening) = match edge {
+                Edge::Open(node) => (node, true),
+                Edge::Close(node) => (node, false),
+            };
+            match node.value() {
+                Node::Element(element) if REMOVED_ELEMENTS.contains(&element.name()) => {
+                    if opening {
+                        removed += 1;
+                    } else {
+                        removed -= 1;
+                    }
+                }
+                Node::Text(text) if opening && removed == 0 => self.text(text),
+                _ => (),
+            }
+        }
+    }
+
+    fn node(&mut self, node: NodeRef) {
+        match node.value() {
+            Node::Text(text) => self.text(text),
+            Node::Element(element) => self.element(node, element),
+            Node::Comment(comment) if comment.trim().eq_ignore_ascii_case("more") => {
+                self.end_block();
+                self.writer.truncate_here();
+            }
+            Node::Document | Node::Fragment => self.children(node),
+            Node::Comment(_) | Node::Doctype(_) | Node::ProcessingInstruction(_) => (),
+        }
+    }
+
+    fn element(&mut self, node: NodeRef, element: &Element) {
+        match element.name() {
+            "html" | "body" | "thead" | "tbody" | "tfoot" => self.children(node),
+            "p" | "div" | "section" | "article" | "header" | "footer" | "main" | "aside"
+            | "figure" | "figcaption" | "center" | "dl" | "dt" | "dd" => self.block(node),
+            "h1" => self.heading(node, ContentSubtype::HeadingOne),
+            "h2" | "h3" | "h4" | "h5" | "h6" => self.heading(node, ContentSubtype::HeadingTwo),
+            "blockquote" => {
+                self.end_block();
+                self.context.quote_depth = self.context.quote_depth.saturating_add(1);
+                self.children(node);
+                self.end_block();
+                self.context.quote_depth = self.context.quote_depth.saturating_sub(1);
+            }
+            name @ ("ul" | "ol") => {
+                self.end_block();
+                self.context.lists.push(name == "ol");
+                self.children(node);
+                self.end_block();
+                self.context.lists.pop();
+            }
+            "li" => self.block(node),
+            "pre" => {
+                self.end_block();
+                self.context.preformatted = true;
+                self.children(node);
+                self.writer.trim_end_matches(|character| character == '\n');
+                self.writer.flush();
+                self.context.preformatted = false;
+            }
+            "br" => self.push_text("\n"),
+            "b" | "strong" => self.styled(node, Some(TextStyle::Bold)),
+            "i" | "em" => self.styled(node, Some(TextStyle::Italic)),
+            "s" | "del" | "strike" => self.styled(node, Some(TextStyle::Strikethrough)),
+            "small" => self.styled(node, Some(TextStyle::Small)),
+            "a" => match element.attr("href") {
+                Some(href) => {
+                    let style = self.url(href).map(TextStyle::Link);
+                    self.styled(node, style);
+                }
+                None => self.children(node),
+            },
+            "span" | "font" => match text_color(element) {
+                Some(color) if is_hex_color(&color) => {
+                    self.styled(node, Some(TextStyle::Color(color)))
+                }
+                Some(color) => {
+                    self.drop_content(DroppedContent::UnsupportedColor(color));
+                    self.children(node);
+                }
+                None => self.children(node),
+            },
+            "img" => self.image(element),
+            name @ ("video" | "audio") => self.media(node, element, name == "video"),
+            name if REMOVED_ELEMENTS.contains(&name) => {
+                self.drop_content(DroppedContent::RemovedElement(name.to_string()));
+            }
+            "hr" => {
+                self.end_block();
+                self.drop_content(DroppedContent::UnsupportedElement("hr".to_string()));
+            }
+            name => {
+                self.drop_content(DroppedContent::UnsupportedElement(name.to_string()));
+                self.children(node);
+            }
+        }
+    }
+
+    /// Text with runs of whitespace collapsed, as a browser would show it.
+    fn text(&mut self, text: &str) {
+        if self.context.preformatted {
+            self.push_text(text);
+            return;
+        }
+
+        let mut collapsed = String::with_capacity(text.len());
+        let mut previous_whitespace = self
+            .writer
+            .text()
+            .is_none_or(|text| text.is_empty() || text.ends_with([' ', '\n']));
+        for character in text.chars() {
+            if character.is_whitespace() {
+                if !previous_whitespace {
+                    collapsed.push(' ');
+                }
+                previous_whitespace = true;
+            } else {
+                collapsed.push(character);
+                previous_whitespace = false;
+            }
+        }
+
+        if !collapsed.is_empty() {
+            self.push_text(&collapsed);
+        }
+    }
+
+    fn push_text(&mut self, text: &str) {
+        let (subtype, indent_level) = self.context.subtype();
+        self.writer.push_text(text, subtype, indent_level);
+    }
+
+    fn end_block(&mut self) {
+        if !self.context.preformatted {
+            self.writer.trim_end_matches(char::is_whitespace);
+        }
+        self.writer.flush();
+    }
+
+    fn block(&mut self, node: NodeRef) {
+        self.end_block();
+        let (subtype, indent_level) = self.context.subtype();
+        self.writer.start_block(subtype, indent_level);
+        self.children(node);
+        self.end_block();
+    }
+
+    fn heading(&mut self, node: NodeRef, subtype: ContentSubtype) {
+        self.end_block();
+        self.context.heading = Some(subtype);
+        self.block(node);
+        self.context.heading = None;
+    }
+
+    fn styled(&mut self, node: NodeRef, style: Option<TextStyle>) {
+        self.writer.open_style(style);
+        self.children(node);
+        self.writer.close_style();
+    }
+
+    fn image(&mut self, element: &Element) {
+        let Some(src) = element.attr("src") else {
+            return;
+        };
+        let Some(url) = self.url(src) else {
+            return;
+        };
+
+        self.end_block();
+        self.writer.push_block(PostContent::Image {
+            media: vec![MediaObject {
+                width: element.attr("width").and_then(|width| width.parse().ok()),
+                height: element
+                    .attr("height")
+                    .and_then(|height| height.parse().ok()),
+                ..MediaObject::from_url(url)
+            }],
+            alt_text: element
+                .attr("alt")
+                .filter(|alt_text| !alt_text.is_empty())
+                .map(str::to_string),
+            caption: None,
+        });
+    }
+
+    fn media(&mut self, node: NodeRef, element: &Element, is_video: bool) {
+        let name = element.name().to_string();
+        // The source may be on the element or on a nested `source`.
+        let src = element.attr("src").or_else(|| {
+            node.children().find_map(|child| match child.value() {
+                Node::Element(child) if child.name() == "source" => child.attr("src"),
+                _ => None,
+            })
+        });
+        let Some(src) = src else {
+            self.drop_content(DroppedContent::RemovedElement(name));
+            return;
+        };
+        let Some(url) = self.url(src) else {
+            return;
+        };
+
+        let media = Some(MediaObject {
+            mime_type: element.attr("type").map(str::to_string),
+            ..MediaObject::from_url(url)
+        });
+        self.end_block();
+        self.writer.push_block(if is_video {
+            PostContent::Video {
+                url: None,
+                media,
+                provider: None,
+                embed_html: None,
+                embed_url: None,
+                poster: None,
+            }
+        } else {
+            PostContent::Audio {
+                url: None,
+                media,
+                provider: None,
+                title: None,
+                artist: None,
+                album: None,
+                poster: None,
+                embed_html: None,
+                embed_url: None,
+            }
+        });
+    }
+}
+
+/// The `color` from a `style` attribute, or a `font` element's `color` attribute.
+fn text_color(element: &Element) -> Option<String> {
+    element
+        .attr("style")
+        .and_then(|style| {
+            style.split(';').find_map(|declaration| {
+                let (property, value) = declaration.split_once(':')?;
+                (property.trim().eq_ignore_ascii_case("color")).then(|| value.trim().to_string())
+            })
+        })
+        .or_else(|| element.attr("color").map(|color| color.trim().to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::post::{html::HtmlRenderer, validate::MAX_INDENT_LEVEL, Formatting, PostCreate};
+
+    fn text_blocks(body: &PostBody) -> Vec<(&str, Option<u8>)> {
+        body.content
+            .iter()
+            .filter_map(|block| match block {
+                PostContent::Text {
+                    text, indent_level, ..
+                } => Some((text.as_str(), *indent_level)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn deep_quotes_are_clamped() {
+        let html = format!(
+            "{}deep{}",
+            "<blockquote>".repeat(300),
+            "</blockquote>".repeat(300)
+        );
+        let import = from_html(&html);
+        assert_eq!(
+            text_blocks(&import.body),
+            [("deep", Some(MAX_INDENT_LEVEL))]
+        );
+        assert!(PostCreate::from(import.body).validate().is_empty());
+    }
+
+    #[test]
+    fn deep_nesting_keeps_text() {
+        let html = format!(
+            "{}<b>deep</b><script>alert(1)</script>{}",
+            "<div>".repeat(2_000),
+            "</div>".repeat(2_000)
+        );
+        let import = from_html(&html);
+        assert_eq!(text_blocks(&import.body), [("deep", None)]);
+        assert!(import.dropped.contains(&DroppedContent::NestingTooDeep));
+    }
+
+    #[test]
+    fn unsafe_links_are_dropped() {
+        let import = from_html(
+            "<p><a href=\"javascript:alert(1)\">click</a> <a href=\"https://example.com/\">ok</a></p>",
+        );
+        let PostContent::Text {
+            text, formatting, ..
+        } = &import.body.content[0]
+        else {
+            panic!("expected text");
+        };
+        assert_eq!(text, "click ok");
+        assert!(matches!(
+            formatting.as_deref(),
+            Some([Formatting::Link {
+                start: 6,
+                end: 8,
+                ..
+            }])
+        ));
+        assert_eq!(
+            import.dropped,
+            [DroppedContent::UnsafeUrl("javascript:alert(1)".to_string())]
+        );
+    }
+
+    #[test]
+    fn unsafe_media_is_dropped() {
+        let import = from_html(
+            "<img src=\"javascript:alert(1)\"><video src=\"data:video/mp4,AAAA\"></video>",
+        );
+        assert!(import.body.content.is_empty());
+        assert_eq!(
+            import.dropped,
+            [
+                DroppedContent::UnsafeUrl("javascript:alert(1)".to_string()),
+                DroppedContent::UnsafeUrl("data:video/mp4,AAAA".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn round_trips_rendered_html() {
+        let html =
+            "<h1>Title</h1><p>Some <b>bold</b> and <a href=\"https://example.com/\">a link</a></p>\
+            <blockquote><blockquote><p>nested</p></blockquote></blockquote>\
+            <ul><li>one<ul><li>two</li></ul></li></ul>";
+        let import = from_html(html);
+        assert!(import.dropped.is_empty());
+
+        let rendered = HtmlRenderer::new().render(&import.body.content, &import.body.layout);
+        let reimport = from_html(&rendered);
+        assert!(reimport.dropped.is_empty());
+        assert_eq!(
+            format!("{:?}", reimport.body.content),
+            format!("{:?}", import.body.content)
+        );
+    }
+}
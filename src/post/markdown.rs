@@ -0,0 +1,480 @@
+#[cfg(feature = "markdown")]
+mod import;
+
+#[cfg(feature = "markdown")]
+pub use self::import::*;
+
+use reqwest::Url;
+
+use super::{
+    formatted_spans,
+    html::{escape_html, has_tag},
+    text::{join_blocks, nest_trail, ListMarkers},
+    validate::is_safe_url,
+    ContentSubtype, DisplayRows, FormattedSpan, Formatting, PostContent, PostGet, PostLayout,
+};
+
+/// Exports NPF content as CommonMark.
+///
+/// Styles without a Markdown equivalent, such as small or colored text, are kept as
+/// inline HTML. The "keep reading" fold becomes a thematic break (`---`).
+pub fn to_markdown(content: &[PostContent], layout: &[PostLayout]) -> String {
+    let display = DisplayRows::new(layout, content.len());
+    let mut blocks = Vec::new();
+    let mut markers = ListMarkers::default();
+
+    for (index, row) in display.rows.iter().enumerate() {
+        for block in row.iter().map(|block| &content[*block]) {
+            let (markdown, is_list_item) = match block {
+                PostContent::Text {
+                    text,
+                    subtype,
+                    indent_level,
+                    formatting,
+                } => {
+                    let indent_level = indent_level.unwrap_or_default() as usize;
+                    let formatting = formatting.as_deref().unwrap_or_default();
+                    match subtype {
+                        Some(subtype) if subtype.is_list_item() => {
+                            let marker = markers
+                                .next(*subtype == ContentSubtype::OrderedListItem, indent_level);
+                            let indent = "    ".repeat(indent_level);
+                            // Later lines line up with the item's text.
+                            let continuation = format!("\\\n{indent}{}", " ".repeat(marker.len()));
+                            (
+                                format!(
+                                    "{indent}{marker}{}",
+                                    escape_lines(&render_markdown_text(text, formatting))
+                                        .join(&continuation)
+                                ),
+                                true,
+                            )
+                        }
+                        Some(ContentSubtype::Chat) => {
+                            let fence = code_fence(text);
+                            (format!("{fence}\n{text}\n{fence}"), false)
+                        }
+                        _ => (
+                            render_text_block(text, formatting, *subtype, indent_level),
+                            false,
+                        ),
+                    }
+                }
+                PostContent::Image {
+                    media,
+                    alt_text,
+                    caption,
+                } => (
+                    media
+                        .first()
+                        .and_then(|media| media.url.as_ref())
+                        .filter(|url| is_safe_url(url))
+                        .map(|url| render_image(url, alt_text.as_deref(), caption.as_deref()))
+                        .unwrap_or_default(),
+                    false,
+                ),
+                PostContent::Audio {
+                    url, media, title, ..
+                } => (
+                    url.as_ref()
+                        .or(media.as_ref().and_then(|media| media.url.as_ref()))
+                        .filter(|url| is_safe_url(url))
+                        .map(|url| render_link(title.as_deref(), url))
+                        .unwrap_or_default(),
+                    false,
+                ),
+                PostContent::Video { url, media, .. } => (
+                    url.as_ref()
+                        .or(media.as_ref().and_then(|media| media.url.as_ref()))
+                        .filter(|url| is_safe_url(url))
+                        .map(|url| render_link(None, url))
+                        .unwrap_or_default(),
+                    false,
+                ),
+                PostContent::Link | PostContent::Paywall => (String::new(), false),
+            };
+
+            if !is_list_item {
+                markers.reset();
+            }
+            if !markdown.is_empty() {
+                blocks.push((markdown, is_list_item));
+            }
+        }
+
+        if display.truncate_after == Some(index) && index + 1 < display.rows.len() {
+            markers.reset();
+            blocks.push(("---".to_string(), false));
+        }
+    }
+
+    join_blocks(blocks)
+}
+
+fn render_text_block(
+    text: &str,
+    formatting: &[Formatting],
+    subtype: Option<ContentSubtype>,
+    indent_level: usize,
+) -> String {
+    let markdown = render_markdown_text(text, formatting);
+    let prefix = match subtype {
+        // A heading can't span lines, so its lines are joined into one.
+        Some(ContentSubtype::HeadingOne) => return format!("# {}", join_heading(&markdown)),
+        Some(ContentSubtype::HeadingTwo) => return format!("## {}", join_heading(&markdown)),
+        Some(ContentSubtype::Quote) => "> ".to_string(),
+        Some(ContentSubtype::Indented) => "> ".repeat(indent_level + 1),
+        _ => String::new(),
+    };
+
+    // Hard breaks, as a plain line break would be read back as a space.
+    escape_lines(&markdown)
+        .into_iter()
+        .map(|line| format!("{prefix}{line}"))
+        .collect::<Vec<_>>()
+        .join("\\\n")
+}
+
+fn join_heading(markdown: &str) -> String {
+    markdown
+        .split('\n')
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn render_image(url: &Url, alt_text: Option<&str>, caption: Option<&str>) -> String {
+    let alt_text = escape(alt_text.unwrap_or_default());
+    let destination = destination(url);
+    match caption {
+        Some(caption) => format!(
+            "![{alt_text}]({destination} \"{}\")",
+            caption.replace('"', "\\\"")
+        ),
+        None => format!("![{alt_text}]({destination})"),
+    }
+}
+
+fn render_link(label: Option<&str>, url: &Url) -> String {
+    format!(
+        "[{}]({})",
+        escape(label.unwrap_or(url.as_str())),
+        destination(url)
+    )
+}
+
+/// A link destination in angle brackets, so parentheses and spaces in the URL can't end it.
+fn destination(url: &Url) -> String {
+    let mut destination = String::with_capacity(url.as_str().len() + 2);
+    destination.push('<');
+    for character in url.as_str().chars() {
+        if matches!(character, '<' | '>' | '\\') {
+            destination.push('\\');
+        }
+        destination.push(character);
+    }
+    destination.push('>');
+    destination
+}
+
+/// Where a link or mention goes, `None` when it's left as plain text.
+fn link_url(formatting: &Formatting) -> Option<Url> {
+    match formatting {
+        Formatting::Link { url, .. } => Some(url.clone()).filter(is_safe_url),
+        Formatting::Mention { blog, .. } => blog.url().filter(is_safe_url),
+        _ => None,
+    }
+}
+
+fn open_marker(formatting: &Formatting) -> String {
+    if !has_tag(formatting) {
+        return String::new();
+    }
+    match formatting {
+        Formatting::Bold { .. } => "**".to_string(),
+        Formatting::Italic { .. } => "*".to_string(),
+        Formatting::Strikethrough { .. } => "~~".to_string(),
+        Formatting::Small { .. } => "<small>".to_string(),
+        Formatting::Link { .. } | Formatting::Mention { .. } => match link_url(formatting) {
+            Some(_) => "[".to_string(),
+            None => String::new(),
+        },
+        Formatting::Color { hex, .. } => {
+            format!("<span style=\"color: {}\">", escape_html(hex))
+        }
+    }
+}
+
+fn close_marker(formatting: &Formatting) -> String {
+    if !has_tag(formatting) {
+        return String::new();
+    }
+    match formatting {
+        Formatting::Bold { .. } => "**".to_string(),
+        Formatting::Italic { .. } => "*".to_string(),
+        Formatting::Strikethrough { .. } => "~~".to_string(),
+        Formatting::Small { .. } => "</small>".to_string(),
+        Formatting::Link { .. } | Formatting::Mention { .. } => match link_url(formatting) {
+            Some(url) => format!("]({})", destination(&url)),
+            None => String::new(),
+        },
+        Formatting::Color { .. } => "</span>".to_string(),
+    }
+}
+
+/// Applies formatting ranges to text as Markdown, escaping the text itself.
+pub fn render_markdown_text(text: &str, formatting: &[Formatting]) -> String {
+    let mut markdown = String::new();
+    for span in formatted_spans(text, formatting) {
+        match span {
+            FormattedSpan::Open(formatting) => markdown.push_str(&open_marker(formatting)),
+            FormattedSpan::Close(formatting) => markdown.push_str(&close_marker(formatting)),
+            FormattedSpan::Text(text) => markdown.push_str(&escape(text)),
+        }
+    }
+    markdown
+}
+
+/// Escapes characters Markdown would treat as inline syntax.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for character in text.chars() {
+        if matches!(
+            character,
+            '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '#' | '~' | '|' | '!'
+        ) {
+            escaped.push('\\');
+        }
+        escaped.push(character);
+    }
+    escaped
+}
+
+/// Splits text into lines, escaping each so none is read as the start of a block.
+fn escape_lines(text: &str) -> Vec<String> {
+    text.split('\n').map(escape_line_start).collect()
+}
+
+/// Stops a line of text from being read as a list item, heading underline or thematic break.
+fn escape_line_start(line: &str) -> String {
+    let (indent, line) = line.split_at(line.len() - line.trim_start_matches(' ').len());
+    if line.starts_with(['-', '+', '=']) {
+        return format!("{indent}\\{line}");
+    }
+
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    if digits > 0 && line[digits..].starts_with(['.', ')']) {
+        return format!("{indent}{}\\{}", &line[..digits], &line[digits..]);
+    }
+    format!("{indent}{line}")
+}
+
+/// A code fence longer than any run of backticks in `text`, so the text can't close it.
+fn code_fence(text: &str) -> String {
+    let longest_run = text
+        .split(|character| character != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or_default();
+    "`".repeat(longest_run.max(2) + 1)
+}
+
+impl PostGet {
+    /// The post as Markdown, with the reblog trail as nested quotes.
+    pub fn to_markdown(&self) -> String {
+        nest_trail(
+            &self.trail,
+            to_markdown(&self.content, &self.layout),
+            |item| to_markdown(&item.content, &item.layout),
+            |name| format!("**{}**:", escape(name)),
+        )
+    }
+}
+
+#[cfg(all(test, feature = "markdown"))]
+mod tests {
+    use super::*;
+
+    fn text(text: &str, subtype: Option<ContentSubtype>, indent_level: Option<u8>) -> PostContent {
+        PostContent::Text {
+            text: text.to_string(),
+            subtype,
+            indent_level,
+            formatting: None,
+        }
+    }
+
+    /// Exports `content` and imports it again.
+    fn round_trip(content: &[PostContent]) -> Vec<PostContent> {
+        from_markdown(&to_markdown(content, &[])).body.content
+    }
+
+    fn texts(content: &[PostContent]) -> Vec<(&str, Option<ContentSubtype>, Option<u8>)> {
+        content
+            .iter()
+            .map(|block| match block {
+                PostContent::Text {
+                    text,
+                    subtype,
+                    indent_level,
+                    ..
+                } => (text.as_str(), *subtype, *indent_level),
+                _ => panic!("expected text"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn every_line_is_escaped() {
+        let content = [text(
+            "first line\n- not a list\n# not heading\n2. nor this",
+            None,
+            None,
+        )];
+        assert_eq!(
+            texts(&round_trip(&content)),
+            [(
+                "first line\n- not a list\n# not heading\n2. nor this",
+                None,
+                None
+            )]
+        );
+    }
+
+    #[test]
+    fn list_item_lines_are_escaped() {
+        let content = [text(
+            "item\n- not nested",
+            Some(ContentSubtype::UnorderedListItem),
+            None,
+        )];
+        assert_eq!(
+            texts(&round_trip(&content)),
+            [(
+                "item\n- not nested",
+                Some(ContentSubtype::UnorderedListItem),
+                None
+            )]
+        );
+    }
+
+    #[test]
+    fn chat_fence_outlasts_backticks() {
+        let content = [
+            text("a\n```\n# b", Some(ContentSubtype::Chat), None),
+            text("after", None, None),
+        ];
+        assert_eq!(
+            texts(&round_trip(&content)),
+            [
+                ("a\n```\n# b", Some(ContentSubtype::Chat), None),
+                ("after", None, None)
+            ]
+        );
+    }
+
+    #[test]
+    fn nesting_survives() {
+        let content = [
+            text("one", Some(ContentSubtype::UnorderedListItem), None),
+            text("two", Some(ContentSubtype::UnorderedListItem), Some(1)),
+            text("three", Some(ContentSubtype::OrderedListItem), Some(2)),
+        ];
+        assert_eq!(
+            texts(&round_trip(&content)),
+            [
+                ("one", Some(ContentSubtype::UnorderedListItem), None),
+                ("two", Some(ContentSubtype::UnorderedListItem), Some(1)),
+                ("three", Some(ContentSubtype::OrderedListItem), Some(2)),
+            ]
+        );
+    }
+
+    #[test]
+    fn unsafe_colors_and_links_are_plain_text() {
+        let formatting = [
+            Formatting::Color {
+                start: 0,
+                end: 3,
+                hex: "red;background:url(https://evil.example/x)".to_string(),
+            },
+            Formatting::Link {
+                start: 4,
+                end: 8,
+                url: "javascript:alert(1)".parse().unwrap(),
+            },
+            Formatting::Mention {
+                start: 9,
+                end: 12,
+                blog: serde_json::from_value(serde_json::json!({
+                    "uuid": "t:bob",
+                    "name": "bob",
+                    "url": "javascript:alert(1)",
+                }))
+                .unwrap(),
+            },
+        ];
+        assert_eq!(
+            render_markdown_text("red link bob", &formatting),
+            "red link bob"
+        );
+    }
+
+    #[test]
+    fn links_with_parentheses_survive() {
+        let url: Url = "https://en.wikipedia.org/wiki/A_(letter)".parse().unwrap();
+        let content = [PostContent::Text {
+            text: "x".to_string(),
+            subtype: None,
+            indent_level: None,
+            formatting: Some(vec![Formatting::Link {
+                start: 0,
+                end: 1,
+                url: url.clone(),
+            }]),
+        }];
+        let round_tripped = round_trip(&content);
+        let PostContent::Text {
+            text, formatting, ..
+        } = &round_tripped[0]
+        else {
+            panic!("expected text");
+        };
+        assert_eq!(text, "x");
+        assert!(matches!(
+            formatting.as_deref(),
+            Some([Formatting::Link { start: 0, end: 1, url: link }]) if *link == url
+        ));
+
+        let unbalanced: Url = "https://en.wikipedia.org/wiki/A)".parse().unwrap();
+        let markdown = render_link(Some("x"), &unbalanced);
+        let imported = from_markdown(&markdown).body.content;
+        assert!(matches!(
+            &imported[0],
+            PostContent::Text { text, formatting: Some(formatting), .. }
+                if text == "x" && matches!(
+                    formatting.as_slice(),
+                    [Formatting::Link { url, .. }] if *url == unbalanced
+                )
+        ));
+    }
+
+    #[test]
+    fn quote_lines_survive() {
+        let content = [
+            text("a\n\nb", Some(ContentSubtype::Quote), None),
+            text("c\nd", Some(ContentSubtype::Indented), Some(1)),
+        ];
+        assert_eq!(texts(&round_trip(&content)), texts(&content));
+    }
+
+    #[test]
+    fn headings_stay_on_one_line() {
+        let content = [text("a\nb", Some(ContentSubtype::HeadingOne), None)];
+        assert_eq!(
+            texts(&round_trip(&content)),
+            [("a b", Some(ContentSubtype::HeadingOne), None)]
+        );
+    }
+}
@@ -0,0 +1,260 @@
+use pulldown_cmark::{Event, HeadingLevel, Options, Parser, Tag};
+use reqwest::Url;
+
+use crate::post::{
+    validate::is_safe_url,
+    writer::{BlockContext, ContentWriter, DroppedContent},
+    ContentSubtype, MediaObject, PostBody, PostContent, TextStyle,
+};
+
+/// The result of [`from_markdown`].
+#[derive(Debug, Default)]
+pub struct MarkdownImport {
+    pub body: PostBody,
+    /// Links and images that were left out, their text was kept.
+    pub dropped: Vec<DroppedContent>,
+}
+
+/// Converts CommonMark into NPF content blocks.
+///
+/// The first thematic break (`---`) marks where the post is cut off by "keep reading".
+/// Anything without an NPF equivalent, such as raw HTML, is left out. Nesting deeper than
+/// Tumblr allows is flattened onto the deepest indent level.
+pub fn from_markdown(markdown: &str) -> MarkdownImport {
+    let mut converter = MarkdownConverter::default();
+    for event in Parser::new_ext(markdown, Options::ENABLE_STRIKETHROUGH) {
+        converter.event(event);
+    }
+    MarkdownImport {
+        body: converter.writer.finish(),
+        dropped: converter.dropped,
+    }
+}
+
+#[derive(Debug)]
+struct PendingImage {
+    url: String,
+    title: String,
+    alt_text: String,
+}
+
+#[derive(Debug, Default)]
+struct MarkdownConverter {
+    writer: ContentWriter,
+    context: BlockContext,
+    image: Option<PendingImage>,
+    dropped: Vec<DroppedContent>,
+}
+
+impl MarkdownConverter {
+    fn drop_content(&mut self, dropped: DroppedContent) {
+        if !self.dropped.contains(&dropped) {
+            self.dropped.push(dropped);
+        }
+    }
+
+    /// An absolute URL that's safe to link to, anything else is reported as dropped.
+    fn url(&mut self, url: &str) -> Option<Url> {
+        match Url::parse(url) {
+            Ok(parsed) if is_safe_url(&parsed) => Some(parsed),
+            Ok(_) => {
+                self.drop_content(DroppedContent::UnsafeUrl(url.to_string()));
+                None
+            }
+            Err(_) => {
+                self.drop_content(DroppedContent::InvalidUrl(url.to_string()));
+                None
+            }
+        }
+    }
+
+    fn event(&mut self, event: Event) {
+        match event {
+            Event::Start(tag) => self.start(tag),
+            Event::End(tag) => self.end(tag),
+            Event::Text(text) | Event::Code(text) => self.push_text(&text),
+            Event::SoftBreak => self.push_text(" "),
+            Event::HardBreak => self.push_text("\n"),
+            Event::Rule => self.writer.truncate_here(),
+            Event::Html(_) | Event::FootnoteReference(_) | Event::TaskListMarker(_) => (),
+        }
+    }
+
+    fn start(&mut self, tag: Tag) {
+        match tag {
+            Tag::Paragraph | Tag::Item => {
+                let (subtype, indent_level) = self.context.subtype();
+                self.writer.start_block(subtype, indent_level);
+            }
+            Tag::Heading(level, _, _) => {
+                self.writer.flush();
+                self.context.heading = Some(match level {
+                    HeadingLevel::H1 => ContentSubtype::HeadingOne,
+                    _ => ContentSubtype::HeadingTwo,
+                });
+            }
+            Tag::BlockQuote => {
+                self.writer.flush();
+                self.context.quote_depth = self.context.quote_depth.saturating_add(1);
+            }
+            Tag::CodeBlock(_) => {
+                self.writer.flush();
+                self.context.preformatted = true;
+            }
+            Tag::List(first_number) => {
+                self.writer.flush();
+                self.context.lists.push(first_number.is_some());
+            }
+            Tag::Emphasis => self.writer.open_style(Some(TextStyle::Italic)),
+            Tag::Strong => self.writer.open_style(Some(TextStyle::Bold)),
+            Tag::Strikethrough => self.writer.open_style(Some(TextStyle::Strikethrough)),
+            Tag::Link(_, url, _) => {
+                let style = self.url(&url).map(TextStyle::Link);
+                self.writer.open_style(style);
+            }
+            Tag::Image(_, url, title) => {
+                self.writer.flush();
+                self.image = Some(PendingImage {
+                    url: url.to_string(),
+                    title: title.to_string(),
+                    alt_text: String::new(),
+                });
+            }
+            Tag::FootnoteDefinition(_)
+            | Tag::Table(_)
+            | Tag::TableHead
+            | Tag::TableRow
+            | Tag::TableCell => (),
+        }
+    }
+
+    fn end(&mut self, tag: Tag) {
+        match tag {
+            Tag::Paragraph | Tag::Item => self.writer.flush(),
+            Tag::Heading(..) => {
+                self.writer.flush();
+                self.context.heading = None;
+            }
+            Tag::BlockQuote => {
+                self.writer.flush();
+                self.context.quote_depth = self.context.quote_depth.saturating_sub(1);
+            }
+            Tag::CodeBlock(_) => {
+                self.writer.trim_end_matches(|character| character == '\n');
+                self.writer.flush();
+                self.context.preformatted = false;
+            }
+            Tag::List(_) => {
+                self.writer.flush();
+                self.context.lists.pop();
+            }
+            Tag::Emphasis | Tag::Strong | Tag::Strikethrough | Tag::Link(..) => {
+                self.writer.close_style()
+            }
+            Tag::Image(..) => self.end_image(),
+            Tag::FootnoteDefinition(_)
+            | Tag::Table(_)
+            | Tag::TableHead
+            | Tag::TableRow
+            | Tag::TableCell => (),
+        }
+    }
+
+    fn end_image(&mut self) {
+        let Some(image) = self.image.take() else {
+            return;
+        };
+
+        match self.url(&image.url) {
+            Some(url) => self.writer.push_block(PostContent::Image {
+                media: vec![MediaObject::from_url(url)],
+                alt_text: (!image.alt_text.is_empty()).then_some(image.alt_text),
+                caption: (!image.title.is_empty()).then_some(image.title),
+            }),
+            // Relative images can't be hosted by Tumblr, keep the description instead.
+            None => self.push_text(&image.alt_text),
+        }
+    }
+
+    fn push_text(&mut self, text: &str) {
+        if let Some(image) = &mut self.image {
+            image.alt_text.push_str(text);
+        } else {
+            let (subtype, indent_level) = self.context.subtype();
+            self.writer.push_text(text, subtype, indent_level);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::post::{validate::MAX_INDENT_LEVEL, Formatting, PostCreate};
+
+    fn indent_levels(body: &PostBody) -> Vec<Option<u8>> {
+        body.content
+            .iter()
+            .map(|block| match block {
+                PostContent::Text { indent_level, .. } => *indent_level,
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn deep_quotes_are_clamped() {
+        let markdown = format!("{} deep", ">".repeat(300));
+        let import = from_markdown(&markdown);
+        assert_eq!(indent_levels(&import.body), [Some(MAX_INDENT_LEVEL)]);
+        assert!(PostCreate::from(import.body).validate().is_empty());
+    }
+
+    #[test]
+    fn deep_lists_are_clamped() {
+        let markdown = (0..12)
+            .map(|depth| format!("{}- item {depth}", "  ".repeat(depth)))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let import = from_markdown(&markdown);
+        let levels = indent_levels(&import.body);
+        assert_eq!(levels.len(), 12);
+        assert_eq!(levels[0], None);
+        assert_eq!(levels[3], Some(3));
+        assert_eq!(levels[11], Some(MAX_INDENT_LEVEL));
+        assert!(PostCreate::from(import.body).validate().is_empty());
+    }
+
+    #[test]
+    fn unsafe_links_are_dropped() {
+        let import = from_markdown("[click](javascript:alert(1)) [ok](https://example.com/)");
+        let PostContent::Text {
+            text, formatting, ..
+        } = &import.body.content[0]
+        else {
+            panic!("expected text");
+        };
+        assert_eq!(text, "click ok");
+        assert!(matches!(
+            formatting.as_deref(),
+            Some([Formatting::Link {
+                start: 6,
+                end: 8,
+                ..
+            }])
+        ));
+        assert_eq!(
+            import.dropped,
+            [DroppedContent::UnsafeUrl("javascript:alert(1)".to_string())]
+        );
+    }
+
+    #[test]
+    fn unsafe_images_keep_their_description() {
+        let import = from_markdown("![a cat](javascript:alert(1))");
+        assert!(matches!(
+            import.body.content.as_slice(),
+            [PostContent::Text { text, .. }] if text == "a cat"
+        ));
+        assert_eq!(import.dropped.len(), 1);
+    }
+}
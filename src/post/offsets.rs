@@ -0,0 +1,257 @@
+use std::ops::Range;
+
+use snafu::prelude::*;
+
+use super::{Formatting, PostContent};
+
+/// How positions in text are counted.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum OffsetUnit {
+    /// One per Unicode scalar value, which is what Tumblr uses for formatting ranges.
+    #[default]
+    CodePoint,
+    /// One per UTF-16 code unit, so characters outside the BMP such as most emoji count twice.
+    ///
+    /// This is how JavaScript measures strings.
+    Utf16,
+}
+
+impl OffsetUnit {
+    /// The unit Tumblr uses for [`Formatting`] ranges.
+    pub const TUMBLR: Self = Self::CodePoint;
+
+    fn width(self, character: char) -> u32 {
+        match self {
+            Self::CodePoint => 1,
+            Self::Utf16 => character.len_utf16() as u32,
+        }
+    }
+}
+
+/// Converts between byte indices of a Rust string and offsets into it as Tumblr counts them.
+///
+/// ```
+/// # use tumblr_api::post::TextOffsets;
+/// let text = "héllo 👋 world";
+/// let offsets = TextOffsets::new(text);
+/// let world = offsets.find("world").unwrap();
+/// assert_eq!(world, 8..13);
+/// assert_eq!(offsets.slice(world), Some("world"));
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct TextOffsets<'a> {
+    text: &'a str,
+    unit: OffsetUnit,
+}
+
+impl<'a> TextOffsets<'a> {
+    pub fn new(text: &'a str) -> Self {
+        Self::with_unit(text, OffsetUnit::TUMBLR)
+    }
+
+    pub fn with_unit(text: &'a str, unit: OffsetUnit) -> Self {
+        Self { text, unit }
+    }
+
+    /// The length of the text in offsets.
+    pub fn len(&self) -> u32 {
+        self.text
+            .chars()
+            .map(|character| self.unit.width(character))
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    /// `None` if `byte_index` is out of bounds or not on a character boundary.
+    pub fn from_byte(&self, byte_index: usize) -> Option<u32> {
+        if !self.text.is_char_boundary(byte_index) {
+            return None;
+        }
+        Some(
+            self.text[..byte_index]
+                .chars()
+                .map(|character| self.unit.width(character))
+                .sum(),
+        )
+    }
+
+    /// `None` if `offset` is out of bounds or falls inside a character.
+    pub fn to_byte(&self, offset: u32) -> Option<usize> {
+        let mut position = 0;
+        for (byte_index, character) in self.text.char_indices() {
+            if position == offset {
+                return Some(byte_index);
+            }
+            if position > offset {
+                return None;
+            }
+            position += self.unit.width(character);
+        }
+        (position == offset).then_some(self.text.len())
+    }
+
+    /// [`to_byte`](Self::to_byte) for ascending offsets, in one pass over the text rather
+    /// than one per offset.
+    pub(crate) fn byte_indices(&self, offsets: &[u32]) -> Vec<Option<usize>> {
+        debug_assert!(offsets.windows(2).all(|pair| pair[0] <= pair[1]));
+        let mut characters = self.text.char_indices().peekable();
+        let mut position = 0;
+        offsets
+            .iter()
+            .map(|&offset| {
+                while position < offset {
+                    let Some((_, character)) = characters.next() else {
+                        break;
+                    };
+                    position += self.unit.width(character);
+                }
+                (position == offset).then(|| {
+                    characters
+                        .peek()
+                        .map_or(self.text.len(), |(byte_index, _)| *byte_index)
+                })
+            })
+            .collect()
+    }
+
+    pub fn from_byte_range(&self, range: Range<usize>) -> Option<Range<u32>> {
+        Some(self.from_byte(range.start)?..self.from_byte(range.end)?)
+    }
+
+    pub fn to_byte_range(&self, range: Range<u32>) -> Option<Range<usize>> {
+        Some(self.to_byte(range.start)?..self.to_byte(range.end)?)
+    }
+
+    /// The range of `slice`, which must be borrowed from the same text.
+    pub fn range_of(&self, slice: &str) -> Option<Range<u32>> {
+        let start = (slice.as_ptr() as usize).checked_sub(self.text.as_ptr() as usize)?;
+        let end = start + slice.len();
+        if end > self.text.len() {
+            return None;
+        }
+        self.from_byte_range(start..end)
+    }
+
+    /// The range of the first occurrence of `needle`.
+    pub fn find(&self, needle: &str) -> Option<Range<u32>> {
+        let start = self.text.find(needle)?;
+        self.from_byte_range(start..start + needle.len())
+    }
+
+    /// The text covered by an offset range.
+    pub fn slice(&self, range: Range<u32>) -> Option<&'a str> {
+        self.text.get(self.to_byte_range(range)?)
+    }
+}
+
+/// The length of `text` as Tumblr counts it.
+pub fn tumblr_len(text: &str) -> u32 {
+    TextOffsets::new(text).len()
+}
+
+#[derive(Debug, Snafu, Clone, PartialEq, Eq)]
+pub enum FormattingRangeError {
+    #[snafu(display("Formatting range {}..{} is inverted.", start, end))]
+    Inverted { start: u32, end: u32 },
+    #[snafu(display("Formatting range {}..{} is empty.", start, end))]
+    Empty { start: u32, end: u32 },
+    #[snafu(display(
+        "Formatting range {}..{} goes past the end of the text, which is {} long.",
+        start,
+        end,
+        len
+    ))]
+    OutOfBounds { start: u32, end: u32, len: u32 },
+}
+
+impl Formatting {
+    pub fn range(&self) -> Range<u32> {
+        self.start()..self.end()
+    }
+
+    /// The text this formatting applies to.
+    pub fn slice<'a>(&self, text: &'a str) -> Option<&'a str> {
+        TextOffsets::new(text).slice(self.range())
+    }
+
+    /// Checks the range is non-empty, not inverted and inside of `text`.
+    pub fn validate(&self, text: &str) -> Result<(), FormattingRangeError> {
+        let (start, end) = (self.start(), self.end());
+        ensure!(start <= end, InvertedSnafu { start, end });
+        ensure!(start != end, EmptySnafu { start, end });
+        let len = tumblr_len(text);
+        ensure!(end <= len, OutOfBoundsSnafu { start, end, len });
+        Ok(())
+    }
+}
+
+impl PostContent {
+    /// Checks every formatting range of a text block, other blocks are always valid.
+    ///
+    /// Returns the index of each invalid range with why it's invalid.
+    pub fn validate_formatting(&self) -> Vec<(usize, FormattingRangeError)> {
+        match self {
+            PostContent::Text {
+                text,
+                formatting: Some(formatting),
+                ..
+            } => formatting
+                .iter()
+                .enumerate()
+                .filter_map(|(index, formatting)| {
+                    formatting.validate(text).err().map(|error| (index, error))
+                })
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // "a", a combining acute accent, an astral emoji and "z".
+    const TEXT: &str = "a\u{301}\u{1F44B}z";
+
+    #[test]
+    fn counts_code_points() {
+        let offsets = TextOffsets::new(TEXT);
+        assert_eq!(offsets.len(), 4);
+        assert_eq!(offsets.to_byte(1), Some(1));
+        assert_eq!(offsets.to_byte(2), Some(3));
+        assert_eq!(offsets.to_byte(3), Some(7));
+        assert_eq!(offsets.to_byte(4), Some(8));
+        assert_eq!(offsets.to_byte(5), None);
+        assert_eq!(offsets.from_byte(7), Some(3));
+        assert_eq!(offsets.from_byte(5), None);
+        assert_eq!(offsets.slice(0..2), Some("a\u{301}"));
+        assert_eq!(offsets.slice(2..3), Some("\u{1F44B}"));
+    }
+
+    #[test]
+    fn counts_utf16_units() {
+        let offsets = TextOffsets::with_unit(TEXT, OffsetUnit::Utf16);
+        assert_eq!(offsets.len(), 5);
+        assert_eq!(offsets.to_byte(3), None);
+        assert_eq!(offsets.to_byte(4), Some(7));
+        assert_eq!(offsets.from_byte(8), Some(5));
+        assert_eq!(offsets.find("z"), Some(4..5));
+    }
+
+    #[test]
+    fn byte_indices_match_to_byte() {
+        for unit in [OffsetUnit::CodePoint, OffsetUnit::Utf16] {
+            let offsets = TextOffsets::with_unit(TEXT, unit);
+            let points = [0, 0, 1, 2, 3, 3, 4, 5, 6];
+            let expected = points
+                .iter()
+                .map(|point| offsets.to_byte(*point))
+                .collect::<Vec<_>>();
+            assert_eq!(offsets.byte_indices(&points), expected);
+        }
+    }
+}
@@ -0,0 +1,208 @@
+use std::{fmt, str::FromStr};
+
+use serde::{
+    de::{self, SeqAccess, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use snafu::prelude::*;
+
+#[derive(Debug, Snafu, Clone, PartialEq, Eq)]
+pub enum TagError {
+    #[snafu(display("Tag '{}' contains a comma, which Tumblr uses to separate tags.", tag))]
+    ContainsComma { tag: String },
+    #[snafu(display("Tags can't be empty."))]
+    EmptyTag,
+}
+
+/// A post's tags.
+///
+/// Sent to Tumblr as a comma separated string, and read back from an array.
+/// A leading `#` is removed from each tag, as Tumblr adds its own.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Tags(Vec<String>);
+
+impl Tags {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn try_from_iter<T: AsRef<str>>(
+        tags: impl IntoIterator<Item = T>,
+    ) -> Result<Self, TagError> {
+        let mut collected = Self::new();
+        for tag in tags {
+            collected.push(tag)?;
+        }
+        Ok(collected)
+    }
+
+    pub fn push(&mut self, tag: impl AsRef<str>) -> Result<(), TagError> {
+        self.0.push(Self::normalize(tag.as_ref())?);
+        Ok(())
+    }
+
+    fn normalize(tag: &str) -> Result<String, TagError> {
+        let tag = tag.trim().trim_start_matches('#').trim();
+        ensure!(!tag.is_empty(), EmptyTagSnafu);
+        ensure!(!tag.contains(','), ContainsCommaSnafu { tag });
+        Ok(tag.to_string())
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn contains(&self, tag: &str) -> bool {
+        self.0.iter().any(|existing| existing == tag)
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, String> {
+        self.0.iter()
+    }
+
+    pub fn as_slice(&self) -> &[String] {
+        &self.0
+    }
+
+    pub fn into_vec(self) -> Vec<String> {
+        self.0
+    }
+}
+
+impl fmt::Display for Tags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0.join(","))
+    }
+}
+
+/// Parses Tumblr's comma separated form.
+impl FromStr for Tags {
+    type Err = TagError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::try_from_iter(s.split(',').filter(|tag| !tag.trim().is_empty()))
+    }
+}
+
+impl TryFrom<Vec<String>> for Tags {
+    type Error = TagError;
+
+    fn try_from(value: Vec<String>) -> Result<Self, Self::Error> {
+        Self::try_from_iter(value)
+    }
+}
+
+impl<'a> IntoIterator for &'a Tags {
+    type Item = &'a String;
+    type IntoIter = std::slice::Iter<'a, String>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl IntoIterator for Tags {
+    type Item = String;
+    type IntoIter = std::vec::IntoIter<String>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl Serialize for Tags {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+/// Accepts both an array of tags and the comma separated form.
+///
+/// Tags from Tumblr are kept as they are, without normalizing.
+impl<'de> Deserialize<'de> for Tags {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct TagsVisitor;
+
+        impl<'de> Visitor<'de> for TagsVisitor {
+            type Value = Tags;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("an array of tags or a comma separated string")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let mut tags = Vec::with_capacity(seq.size_hint().unwrap_or_default());
+                while let Some(tag) = seq.next_element()? {
+                    tags.push(tag);
+                }
+                Ok(Tags(tags))
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                v.parse().map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_any(TagsVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tags(tags: &[&str]) -> Tags {
+        Tags::try_from_iter(tags).unwrap()
+    }
+
+    #[test]
+    fn strips_leading_hashes() {
+        assert_eq!(
+            tags(&["#art", " ##my art ", "c#"]).as_slice(),
+            ["art", "my art", "c#"]
+        );
+    }
+
+    #[test]
+    fn rejects_commas_and_empty_tags() {
+        assert_eq!(
+            Tags::try_from_iter(["a,b"]),
+            Err(TagError::ContainsComma {
+                tag: "a,b".to_string()
+            })
+        );
+        assert_eq!(Tags::try_from_iter([""]), Err(TagError::EmptyTag));
+        assert_eq!(Tags::try_from_iter([" # "]), Err(TagError::EmptyTag));
+    }
+
+    #[test]
+    fn serializes_comma_separated() {
+        let tags = tags(&["art", "#my art"]);
+        assert_eq!(tags.to_string(), "art,my art");
+        assert_eq!(serde_json::to_value(&tags).unwrap(), "art,my art");
+        assert_eq!(serde_json::to_value(Tags::new()).unwrap(), "");
+    }
+
+    #[test]
+    fn parses_comma_separated() {
+        assert_eq!(
+            "art, #my art,,".parse::<Tags>().unwrap(),
+            tags(&["art", "my art"])
+        );
+    }
+
+    #[test]
+    fn deserializes_arrays_and_strings() {
+        let from_array: Tags = serde_json::from_str(r##"["art", "#kept as is"]"##).unwrap();
+        assert_eq!(from_array.as_slice(), ["art", "#kept as is"]);
+
+        let from_string: Tags = serde_json::from_str(r#""art,my art""#).unwrap();
+        assert_eq!(from_string, tags(&["art", "my art"]));
+
+        assert!(serde_json::from_str::<Tags>("3").is_err());
+    }
+}
@@ -0,0 +1,185 @@
+use super::{ContentSubtype, DisplayRows, PostContent, PostGet, PostLayout, PostTrail};
+
+/// Flattens NPF content into plain text, for search indexes and diffs.
+///
+/// Formatting is dropped, list items keep their markers and media is replaced with its
+/// description.
+pub fn to_plain_text(content: &[PostContent], layout: &[PostLayout]) -> String {
+    let display = DisplayRows::new(layout, content.len());
+    let mut blocks = Vec::new();
+    let mut markers = ListMarkers::default();
+
+    for block in display.rows.iter().flatten().map(|block| &content[*block]) {
+        let (text, is_list_item) = match block {
+            PostContent::Text {
+                text,
+                subtype: Some(subtype),
+                indent_level,
+                ..
+            } if subtype.is_list_item() => {
+                let indent_level = indent_level.unwrap_or_default() as usize;
+                let marker =
+                    markers.next(*subtype == ContentSubtype::OrderedListItem, indent_level);
+                (
+                    format!("{}{marker}{text}", "    ".repeat(indent_level)),
+                    true,
+                )
+            }
+            PostContent::Text { text, .. } => (text.clone(), false),
+            PostContent::Image {
+                alt_text, caption, ..
+            } => (
+                [alt_text.as_deref(), caption.as_deref()]
+                    .into_iter()
+                    .flatten()
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+                false,
+            ),
+            PostContent::Audio {
+                url,
+                media,
+                title,
+                artist,
+                ..
+            } => (
+                [
+                    artist.clone(),
+                    title.clone(),
+                    url.as_ref()
+                        .or(media.as_ref().and_then(|media| media.url.as_ref()))
+                        .map(|url| url.to_string()),
+                ]
+                .into_iter()
+                .flatten()
+                .collect::<Vec<_>>()
+                .join(" - "),
+                false,
+            ),
+            PostContent::Video { url, media, .. } => (
+                url.as_ref()
+                    .or(media.as_ref().and_then(|media| media.url.as_ref()))
+                    .map(|url| url.to_string())
+                    .unwrap_or_default(),
+                false,
+            ),
+            PostContent::Link | PostContent::Paywall => (String::new(), false),
+        };
+
+        if !is_list_item {
+            markers.reset();
+        }
+        if !text.is_empty() {
+            blocks.push((text, is_list_item));
+        }
+    }
+
+    join_blocks(blocks)
+}
+
+/// Joins rendered blocks with blank lines, keeping consecutive list items together.
+pub(crate) fn join_blocks(blocks: Vec<(String, bool)>) -> String {
+    let mut joined = String::new();
+    let mut previous_list_item = false;
+    for (index, (block, is_list_item)) in blocks.into_iter().enumerate() {
+        if index > 0 {
+            joined.push_str(if previous_list_item && is_list_item {
+                "\n"
+            } else {
+                "\n\n"
+            });
+        }
+        joined.push_str(&block);
+        previous_list_item = is_list_item;
+    }
+    joined
+}
+
+/// Numbers list items at each indent level.
+#[derive(Debug, Default)]
+pub(crate) struct ListMarkers {
+    /// Whether the list at each level is ordered, and how many items it has so far.
+    levels: Vec<(bool, u32)>,
+}
+
+impl ListMarkers {
+    /// The marker for the next item, such as `- ` or `2. `.
+    pub fn next(&mut self, ordered: bool, indent_level: usize) -> String {
+        self.levels.truncate(indent_level + 1);
+        while self.levels.len() <= indent_level {
+            self.levels.push((ordered, 0));
+        }
+
+        let level = &mut self.levels[indent_level];
+        if level.0 != ordered {
+            *level = (ordered, 0);
+        }
+        level.1 += 1;
+
+        if ordered {
+            format!("{}. ", level.1)
+        } else {
+            "- ".to_string()
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.levels.clear();
+    }
+}
+
+/// Prefixes every line with `> `.
+pub(crate) fn quote(text: &str) -> String {
+    text.lines()
+        .map(|line| {
+            if line.is_empty() {
+                ">".to_string()
+            } else {
+                format!("> {line}")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Nests each trail item inside a quote of the items before it, as reblogs used to look.
+///
+/// `attribution` renders the header shown above each trail item's content.
+pub(crate) fn nest_trail(
+    trail: &[PostTrail],
+    body: String,
+    mut render: impl FnMut(&PostTrail) -> String,
+    attribution: impl Fn(&str) -> String,
+) -> String {
+    let mut nested = String::new();
+    for item in trail {
+        let mut rendered = render(item);
+        if let Some(name) = item.blog_name() {
+            rendered = format!("{}\n\n{rendered}", attribution(name));
+        }
+
+        nested = if nested.is_empty() {
+            rendered
+        } else {
+            format!("{}\n\n{rendered}", quote(&nested))
+        };
+    }
+
+    match (nested.is_empty(), body.is_empty()) {
+        (true, _) => body,
+        (false, true) => quote(&nested),
+        (false, false) => format!("{}\n\n{body}", quote(&nested)),
+    }
+}
+
+impl PostGet {
+    /// The post as plain text, with the reblog trail as nested quotes.
+    pub fn to_plain_text(&self) -> String {
+        nest_trail(
+            &self.trail,
+            to_plain_text(&self.content, &self.layout),
+            |item| to_plain_text(&item.content, &item.layout),
+            |name| format!("{name}:"),
+        )
+    }
+}
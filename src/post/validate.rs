@@ -0,0 +1,455 @@
+use snafu::prelude::*;
+
+use super::{
+    ContentSubtype, Formatting, FormattingRangeError, PostContent, PostCreate, PostLayout,
+    PostState, Tags,
+};
+
+/// Deepest `indent_level` Tumblr accepts.
+pub const MAX_INDENT_LEVEL: u8 = 7;
+/// Most tags a post can have.
+pub const MAX_TAGS: usize = 30;
+/// Longest a single tag can be, in characters.
+pub const MAX_TAG_LENGTH: usize = 140;
+
+/// A reason Tumblr would reject a post.
+///
+/// `block` is an index into the post's content.
+#[derive(Debug, Snafu, Clone, PartialEq, Eq)]
+pub enum ValidationIssue {
+    #[snafu(display("Block {} formatting {}: {}", block, formatting, source))]
+    FormattingRange {
+        block: usize,
+        formatting: usize,
+        source: FormattingRangeError,
+    },
+    #[snafu(display(
+        "Block {} formatting {} has an invalid color '{}'.",
+        block,
+        formatting,
+        hex
+    ))]
+    InvalidColor {
+        block: usize,
+        formatting: usize,
+        hex: String,
+    },
+    #[snafu(display(
+        "Block {} can't be indented, only list items and indented blocks can.",
+        block
+    ))]
+    IndentLevelNotAllowed { block: usize },
+    #[snafu(display(
+        "Block {} is indented {} levels, at most {} are allowed.",
+        block,
+        indent_level,
+        MAX_INDENT_LEVEL
+    ))]
+    IndentLevelTooDeep { block: usize, indent_level: u8 },
+    #[snafu(display("Block {} has no media.", block))]
+    MissingMedia { block: usize },
+    #[snafu(display("Post has {} tags, at most {} are allowed.", count, MAX_TAGS))]
+    TooManyTags { count: usize },
+    #[snafu(display("Tag '{}' is longer than {} characters.", tag, MAX_TAG_LENGTH))]
+    TagTooLong { tag: String },
+    #[snafu(display("`publish_on` can only be set on queued posts."))]
+    PublishOnWithoutQueue,
+    #[snafu(display("Layout {} references block {} which doesn't exist.", layout, block))]
+    LayoutBlockOutOfRange { layout: usize, block: u32 },
+    #[snafu(display("Layout {} is an ask without any blocks.", layout))]
+    EmptyAskLayout { layout: usize },
+    #[snafu(display("Reblog is missing `{}`.", field))]
+    IncompleteReblogInfo { field: &'static str },
+}
+
+impl PostCreate {
+    /// Checks the post against NPF's constraints before it's sent.
+    ///
+    /// An empty list means no problems were found, Tumblr may still reject the post for
+    /// reasons that can't be checked locally.
+    pub fn validate(&self) -> Vec<ValidationIssue> {
+        let mut issues = Vec::new();
+
+        for (block, content) in self.content.iter().enumerate() {
+            validate_content(block, content, &mut issues);
+        }
+
+        if let Some(tags) = &self.tags {
+            validate_tags(tags, &mut issues);
+        }
+
+        if self.publish_on.is_some() && !matches!(self.state, Some(PostState::Queue)) {
+            issues.push(ValidationIssue::PublishOnWithoutQueue);
+        }
+
+        for (layout, entry) in self.layout.iter().flatten().enumerate() {
+            if matches!(entry, PostLayout::Ask { blocks, .. } if blocks.is_empty()) {
+                issues.push(ValidationIssue::EmptyAskLayout { layout });
+            }
+            issues.extend(
+                layout_blocks(entry)
+                    .into_iter()
+                    .filter(|block| *block as usize >= self.content.len())
+                    .map(|block| ValidationIssue::LayoutBlockOutOfRange { layout, block }),
+            );
+        }
+
+        if let Some(reblog_info) = &self.reblog_info {
+            let missing = [
+                (
+                    "parent_tumblelog_uuid",
+                    reblog_info.parent_tumblelog_uuid.is_empty(),
+                ),
+                ("parent_post_id", reblog_info.parent_post_id == 0),
+                ("reblog_key", reblog_info.reblog_key.is_empty()),
+            ];
+            issues.extend(
+                missing
+                    .into_iter()
+                    .filter(|(_, missing)| *missing)
+                    .map(|(field, _)| ValidationIssue::IncompleteReblogInfo { field }),
+            );
+        }
+
+        issues
+    }
+}
+
+fn validate_content(block: usize, content: &PostContent, issues: &mut Vec<ValidationIssue>) {
+    match content {
+        PostContent::Text {
+            subtype,
+            indent_level,
+            formatting,
+            ..
+        } => {
+            issues.extend(
+                content
+                    .validate_formatting()
+                    .into_iter()
+                    .map(|(formatting, source)| ValidationIssue::FormattingRange {
+                        block,
+                        formatting,
+                        source,
+                    }),
+            );
+
+            for (index, formatting) in formatting.iter().flatten().enumerate() {
+                match formatting {
+                    Formatting::Color { hex, .. } if !is_hex_color(hex) => {
+                        issues.push(ValidationIssue::InvalidColor {
+                            block,
+                            formatting: index,
+                            hex: hex.clone(),
+                        })
+                    }
+                    _ => (),
+                }
+            }
+
+            if let Some(indent_level) = *indent_level {
+                let indentable = subtype.is_some_and(|subtype| {
+                    subtype.is_list_item() || subtype == ContentSubtype::Indented
+                });
+                if !indentable {
+                    issues.push(ValidationIssue::IndentLevelNotAllowed { block });
+                } else if indent_level > MAX_INDENT_LEVEL {
+                    issues.push(ValidationIssue::IndentLevelTooDeep {
+                        block,
+                        indent_level,
+                    });
+                }
+            }
+        }
+        PostContent::Image { media, .. } if media.is_empty() => {
+            issues.push(ValidationIssue::MissingMedia { block });
+        }
+        _ => (),
+    }
+}
+
+fn validate_tags(tags: &Tags, issues: &mut Vec<ValidationIssue>) {
+    if tags.len() > MAX_TAGS {
+        issues.push(ValidationIssue::TooManyTags { count: tags.len() });
+    }
+    issues.extend(
+        tags.iter()
+            .filter(|tag| tag.chars().count() > MAX_TAG_LENGTH)
+            .map(|tag| ValidationIssue::TagTooLong { tag: tag.clone() }),
+    );
+}
+
+/// Every block index a layout refers to.
+fn layout_blocks(layout: &PostLayout) -> Vec<u32> {
+    match layout {
+        PostLayout::Rows {
+            display,
+            truncate_after,
+        } => display
+            .iter()
+            .flat_map(|row| row.blocks.iter().copied())
+            .chain(*truncate_after)
+            .collect(),
+        PostLayout::Condensed {
+            blocks,
+            truncate_after,
+        } => blocks
+            .iter()
+            .flatten()
+            .copied()
+            .chain(*truncate_after)
+            .collect(),
+        PostLayout::Ask { blocks, .. } => blocks.clone(),
+        PostLayout::Unknown => Vec::new(),
+    }
+}
+
+/// `#rgb` or `#rrggbb`.
+pub(crate) fn is_hex_color(color: &str) -> bool {
+    color.strip_prefix('#').is_some_and(|hex| {
+        matches!(hex.len(), 3 | 6) && hex.chars().all(|character| character.is_ascii_hexdigit())
+    })
+}
+
+/// Whether a link is safe to put in an `href`, only `http`, `https` and `mailto` are.
+pub(crate) fn is_safe_url(url: &reqwest::Url) -> bool {
+    matches!(url.scheme(), "http" | "https" | "mailto")
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+    use crate::post::{LayoutRow, ReblogInfo};
+
+    fn text(formatting: Vec<Formatting>) -> PostContent {
+        PostContent::Text {
+            text: "Hello".to_string(),
+            subtype: None,
+            indent_level: None,
+            formatting: Some(formatting),
+        }
+    }
+
+    fn indented(subtype: Option<ContentSubtype>, indent_level: u8) -> PostContent {
+        PostContent::Text {
+            text: "Hello".to_string(),
+            subtype,
+            indent_level: Some(indent_level),
+            formatting: None,
+        }
+    }
+
+    fn issues(post: PostCreate) -> Vec<ValidationIssue> {
+        post.validate()
+    }
+
+    fn with_content(content: Vec<PostContent>) -> Vec<ValidationIssue> {
+        issues(PostCreate {
+            content,
+            ..Default::default()
+        })
+    }
+
+    fn with_tags(tags: impl IntoIterator<Item = String>) -> Vec<ValidationIssue> {
+        issues(PostCreate {
+            tags: Some(Tags::try_from_iter(tags).unwrap()),
+            ..Default::default()
+        })
+    }
+
+    fn with_layout(layout: PostLayout) -> Vec<ValidationIssue> {
+        issues(PostCreate {
+            content: vec![text(Vec::new())],
+            layout: Some(vec![layout]),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn valid_posts_have_no_issues() {
+        assert_eq!(with_content(vec![text(Vec::new())]), []);
+    }
+
+    #[test]
+    fn formatting_range() {
+        assert_eq!(
+            with_content(vec![text(vec![
+                Formatting::Bold { start: 0, end: 5 },
+                Formatting::Italic { start: 2, end: 9 },
+            ])]),
+            [ValidationIssue::FormattingRange {
+                block: 0,
+                formatting: 1,
+                source: FormattingRangeError::OutOfBounds {
+                    start: 2,
+                    end: 9,
+                    len: 5
+                },
+            }]
+        );
+    }
+
+    #[test]
+    fn invalid_color() {
+        let color = |hex: &str| Formatting::Color {
+            start: 0,
+            end: 5,
+            hex: hex.to_string(),
+        };
+        assert_eq!(
+            with_content(vec![text(vec![color("#f00"), color("red")])]),
+            [ValidationIssue::InvalidColor {
+                block: 0,
+                formatting: 1,
+                hex: "red".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn indent_level_not_allowed() {
+        assert_eq!(
+            with_content(vec![
+                indented(Some(ContentSubtype::UnorderedListItem), 1),
+                indented(Some(ContentSubtype::HeadingOne), 1),
+                indented(None, 0),
+            ]),
+            [
+                ValidationIssue::IndentLevelNotAllowed { block: 1 },
+                ValidationIssue::IndentLevelNotAllowed { block: 2 },
+            ]
+        );
+    }
+
+    #[test]
+    fn indent_level_too_deep() {
+        assert_eq!(
+            with_content(vec![
+                indented(Some(ContentSubtype::Indented), MAX_INDENT_LEVEL),
+                indented(Some(ContentSubtype::Indented), MAX_INDENT_LEVEL + 1),
+            ]),
+            [ValidationIssue::IndentLevelTooDeep {
+                block: 1,
+                indent_level: MAX_INDENT_LEVEL + 1,
+            }]
+        );
+    }
+
+    #[test]
+    fn missing_media() {
+        assert_eq!(
+            with_content(vec![PostContent::Image {
+                media: Vec::new(),
+                alt_text: None,
+                caption: None,
+            }]),
+            [ValidationIssue::MissingMedia { block: 0 }]
+        );
+    }
+
+    #[test]
+    fn too_many_tags() {
+        let tags = |count: usize| (0..count).map(|tag| tag.to_string());
+        assert_eq!(with_tags(tags(MAX_TAGS)), []);
+        assert_eq!(
+            with_tags(tags(MAX_TAGS + 1)),
+            [ValidationIssue::TooManyTags {
+                count: MAX_TAGS + 1
+            }]
+        );
+    }
+
+    #[test]
+    fn tag_too_long() {
+        // Counted in characters, not bytes.
+        let longest = "é".repeat(MAX_TAG_LENGTH);
+        let too_long = "a".repeat(MAX_TAG_LENGTH + 1);
+        assert_eq!(
+            with_tags([longest, too_long.clone()]),
+            [ValidationIssue::TagTooLong { tag: too_long }]
+        );
+    }
+
+    #[test]
+    fn publish_on_without_queue() {
+        let post = |state| PostCreate {
+            state,
+            publish_on: Some(Utc::now()),
+            ..Default::default()
+        };
+        assert_eq!(issues(post(Some(PostState::Queue))), []);
+        assert_eq!(
+            issues(post(Some(PostState::Draft))),
+            [ValidationIssue::PublishOnWithoutQueue]
+        );
+        assert_eq!(issues(post(None)), [ValidationIssue::PublishOnWithoutQueue]);
+    }
+
+    #[test]
+    fn layout_block_out_of_range() {
+        assert_eq!(
+            with_layout(PostLayout::Rows {
+                display: vec![LayoutRow {
+                    blocks: vec![0, 1],
+                    mode: None,
+                }],
+                truncate_after: Some(2),
+            }),
+            [
+                ValidationIssue::LayoutBlockOutOfRange {
+                    layout: 0,
+                    block: 1
+                },
+                ValidationIssue::LayoutBlockOutOfRange {
+                    layout: 0,
+                    block: 2
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn empty_ask_layout() {
+        assert_eq!(
+            with_layout(PostLayout::Ask {
+                blocks: Vec::new(),
+                attribution: None,
+            }),
+            [ValidationIssue::EmptyAskLayout { layout: 0 }]
+        );
+        assert_eq!(
+            with_layout(PostLayout::Ask {
+                blocks: vec![0],
+                attribution: None,
+            }),
+            []
+        );
+    }
+
+    #[test]
+    fn incomplete_reblog_info() {
+        let post = PostCreate {
+            reblog_info: Some(ReblogInfo {
+                parent_tumblelog_uuid: "t:staff".to_string(),
+                parent_post_id: 0,
+                reblog_key: String::new(),
+                hide_trail: None,
+                exclude_trail_items: None,
+            }),
+            ..Default::default()
+        };
+        assert_eq!(
+            issues(post),
+            [
+                ValidationIssue::IncompleteReblogInfo {
+                    field: "parent_post_id"
+                },
+                ValidationIssue::IncompleteReblogInfo {
+                    field: "reblog_key"
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,228 @@
+use super::{
+    tumblr_len, validate::MAX_INDENT_LEVEL, ContentSubtype, Formatting, PostBody, PostContent,
+    PostLayout, TextStyle,
+};
+
+#[derive(Debug, Default)]
+struct TextBlock {
+    text: String,
+    subtype: Option<ContentSubtype>,
+    indent_level: Option<u8>,
+    formatting: Vec<Formatting>,
+}
+
+impl TextBlock {
+    fn len(&self) -> u32 {
+        tumblr_len(&self.text)
+    }
+}
+
+/// Something in imported HTML or Markdown that couldn't be carried over into NPF.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DroppedContent {
+    /// An element with no NPF equivalent, its text was kept.
+    UnsupportedElement(String),
+    /// An element that was removed along with everything in it, such as `script`.
+    RemovedElement(String),
+    /// A link or image whose URL couldn't be parsed, as Tumblr needs absolute URLs.
+    InvalidUrl(String),
+    /// A text color that isn't a hex color.
+    UnsupportedColor(String),
+    /// A link or image whose scheme isn't `http`, `https` or `mailto`, such as `javascript:`.
+    UnsafeUrl(String),
+    /// Elements nested too deeply to follow, their text was kept without formatting.
+    NestingTooDeep,
+}
+
+/// Where in the document text is, which decides the subtype of new text blocks.
+#[derive(Debug, Default)]
+pub(crate) struct BlockContext {
+    /// Whether each open list is ordered.
+    pub lists: Vec<bool>,
+    pub quote_depth: usize,
+    pub heading: Option<ContentSubtype>,
+    pub preformatted: bool,
+}
+
+impl BlockContext {
+    /// The subtype and indentation a new text block should have.
+    pub fn subtype(&self) -> (Option<ContentSubtype>, Option<u8>) {
+        if self.preformatted {
+            (Some(ContentSubtype::Chat), None)
+        } else if let Some(heading) = &self.heading {
+            (Some(*heading), None)
+        } else if let Some(ordered) = self.lists.last() {
+            let subtype = if *ordered {
+                ContentSubtype::OrderedListItem
+            } else {
+                ContentSubtype::UnorderedListItem
+            };
+            let indent_level = clamp_indent(self.lists.len() - 1);
+            (Some(subtype), (indent_level > 0).then_some(indent_level))
+        } else if self.quote_depth > 1 {
+            (
+                Some(ContentSubtype::Indented),
+                Some(clamp_indent(self.quote_depth - 1)),
+            )
+        } else if self.quote_depth == 1 {
+            (Some(ContentSubtype::Quote), None)
+        } else {
+            (None, None)
+        }
+    }
+}
+
+/// Nesting deeper than Tumblr allows is flattened onto the deepest level.
+fn clamp_indent(depth: usize) -> u8 {
+    depth.min(MAX_INDENT_LEVEL as usize) as u8
+}
+
+/// Collects content blocks from a stream of text and styles, as produced by the importers.
+#[derive(Debug, Default)]
+pub(crate) struct ContentWriter {
+    content: Vec<PostContent>,
+    block: Option<TextBlock>,
+    /// Open inline styles and where they start in the current block.
+    ///
+    /// `None` is a style that couldn't be converted, it's kept so the stack stays balanced.
+    styles: Vec<(Option<TextStyle>, u32)>,
+    truncate_after: Option<u32>,
+}
+
+impl ContentWriter {
+    /// Starts a new text block, unless the current one is still empty.
+    pub fn start_block(&mut self, subtype: Option<ContentSubtype>, indent_level: Option<u8>) {
+        match &mut self.block {
+            Some(block) if block.text.is_empty() => {
+                block.subtype = subtype;
+                block.indent_level = indent_level;
+            }
+            _ => {
+                self.flush();
+                self.block = Some(TextBlock {
+                    subtype,
+                    indent_level,
+                    ..Default::default()
+                });
+            }
+        }
+    }
+
+    /// The current text, if a block is open.
+    #[cfg(feature = "html")]
+    pub fn text(&self) -> Option<&str> {
+        self.block.as_ref().map(|block| block.text.as_str())
+    }
+
+    /// Appends to the current block, opening one with `subtype` if needed.
+    pub fn push_text(
+        &mut self,
+        text: &str,
+        subtype: Option<ContentSubtype>,
+        indent_level: Option<u8>,
+    ) {
+        if self.block.is_none() {
+            self.start_block(subtype, indent_level);
+        }
+        if let Some(block) = &mut self.block {
+            block.text.push_str(text);
+        }
+    }
+
+    fn position(&self) -> u32 {
+        self.block.as_ref().map_or(0, TextBlock::len)
+    }
+
+    pub fn open_style(&mut self, style: Option<TextStyle>) {
+        self.styles.push((style, self.position()));
+    }
+
+    pub fn close_style(&mut self) {
+        let Some((style, start)) = self.styles.pop() else {
+            return;
+        };
+        if let (Some(style), Some(block)) = (style, &mut self.block) {
+            let end = block.len();
+            if start < end {
+                block.formatting.push(style.into_formatting(start, end));
+            }
+        }
+    }
+
+    /// Removes trailing characters matching `pattern` from the current block.
+    pub fn trim_end_matches(&mut self, pattern: impl Fn(char) -> bool) {
+        if let Some(block) = &mut self.block {
+            block
+                .text
+                .truncate(block.text.trim_end_matches(&pattern).len());
+        }
+    }
+
+    /// Finishes the current text block.
+    ///
+    /// Styles still open carry on at the start of the next block.
+    pub fn flush(&mut self) {
+        let Some(mut block) = self.block.take() else {
+            return;
+        };
+
+        let end = block.len();
+        for (style, start) in &mut self.styles {
+            if let Some(style) = style {
+                if *start < end {
+                    block
+                        .formatting
+                        .push(style.clone().into_formatting(*start, end));
+                }
+            }
+            *start = 0;
+        }
+
+        // Ranges may reach past text that was trimmed off.
+        for formatting in &mut block.formatting {
+            formatting.clamp(end);
+        }
+        block
+            .formatting
+            .retain(|formatting| formatting.start() < formatting.end());
+
+        if !block.text.is_empty() {
+            self.content.push(PostContent::Text {
+                text: block.text,
+                subtype: block.subtype,
+                indent_level: block.indent_level,
+                formatting: (!block.formatting.is_empty()).then_some(block.formatting),
+            });
+        }
+    }
+
+    /// Adds a non-text block, such as an image.
+    pub fn push_block(&mut self, content: PostContent) {
+        self.flush();
+        self.content.push(content);
+    }
+
+    /// Marks the "keep reading" fold after the last block, only the first fold is kept.
+    pub fn truncate_here(&mut self) {
+        self.flush();
+        if self.truncate_after.is_none() && !self.content.is_empty() {
+            self.truncate_after = Some(self.content.len() as u32 - 1);
+        }
+    }
+
+    pub fn finish(mut self) -> PostBody {
+        self.flush();
+
+        let layout = match self.truncate_after {
+            Some(truncate_after) if (truncate_after as usize) + 1 < self.content.len() => {
+                vec![PostLayout::truncated(self.content.len(), truncate_after)]
+            }
+            _ => Vec::new(),
+        };
+
+        PostBody {
+            content: self.content,
+            layout,
+        }
+    }
+}
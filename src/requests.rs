@@ -1,11 +1,28 @@
 pub mod blog;
+#[cfg(feature = "testing")]
+pub mod cassette;
+pub mod media;
 pub mod paths;
+pub mod rate_limit;
 
 use oauth2::{AccessToken, ClientId};
-use reqwest::{header::CONTENT_TYPE, Client, Request, RequestBuilder, Url};
-use serde::{de::DeserializeOwned, Deserialize};
+use reqwest::{
+    header::{HeaderMap, CONTENT_TYPE},
+    Client, Request, RequestBuilder, StatusCode, Url,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
-use super::TumblrClient;
+use snafu::prelude::*;
+
+#[cfg(feature = "testing")]
+use self::cassette::{Cassette, CassetteMode};
+use self::{media::MediaAttachment, rate_limit::RateLimit};
+#[cfg(feature = "testing")]
+use crate::error::CassetteMissSnafu;
+use crate::{
+    error::{ApiError, ApiSnafu, AuthSnafu, DecodeSnafu, Error, HttpSnafu, Result},
+    TumblrClient,
+};
 
 const JSON_HEADER_VALUE: &str = "application/json";
 const API_KEY_HEADER_KEY: &str = "api_key";
@@ -14,6 +31,7 @@ const API_KEY_HEADER_KEY: &str = "api_key";
 pub enum HttpMethod {
     Get,
     Post,
+    Put,
     Delete,
 }
 
@@ -22,6 +40,7 @@ impl From<HttpMethod> for reqwest::Method {
         match value {
             HttpMethod::Get => Self::GET,
             HttpMethod::Post => Self::POST,
+            HttpMethod::Put => Self::PUT,
             HttpMethod::Delete => Self::DELETE,
         }
     }
@@ -32,11 +51,7 @@ pub struct TumblrRequestBuilder {
 }
 
 impl TumblrRequestBuilder {
-    pub fn new(
-        request_client: &Client,
-        method: HttpMethod,
-        url: Url,
-    ) -> Result<Self, Box<dyn std::error::Error>> {
+    pub fn new(request_client: &Client, method: HttpMethod, url: Url) -> Result<Self> {
         Ok(Self {
             builder: request_client.request(method.into(), url),
         })
@@ -50,6 +65,21 @@ impl TumblrRequestBuilder {
         self
     }
 
+    /// Sends the JSON as a `multipart/form-data` body when there are files attached.
+    pub fn json_with_media(mut self, json: String, media: &[MediaAttachment]) -> Result<Self> {
+        if media.is_empty() {
+            return Ok(self.json(json));
+        }
+
+        self.builder = self.builder.multipart(media::create_form(json, media)?);
+        Ok(self)
+    }
+
+    pub fn query<T: Serialize + ?Sized>(mut self, query: &T) -> Self {
+        self.builder = self.builder.query(query);
+        self
+    }
+
     pub fn auth_by_key(mut self, key: &ClientId) -> Self {
         self.builder = self.builder.query(&[(API_KEY_HEADER_KEY, key)]);
         self
@@ -60,8 +90,8 @@ impl TumblrRequestBuilder {
         self
     }
 
-    pub fn build(self) -> Result<Request, reqwest::Error> {
-        self.builder.build()
+    pub fn build(self) -> Result<Request> {
+        self.builder.build().context(HttpSnafu)
     }
 }
 
@@ -71,6 +101,17 @@ pub struct TumblrResponseMeta {
     pub msg: String,
 }
 
+/// The status in a response's `meta`, if it has one.
+fn envelope_status(body: &str) -> Option<StatusCode> {
+    #[derive(Deserialize)]
+    struct Envelope {
+        meta: TumblrResponseMeta,
+    }
+
+    let envelope = serde_json::from_str::<Envelope>(body).ok()?;
+    StatusCode::from_u16(envelope.meta.status).ok()
+}
+
 #[derive(Debug, Deserialize)]
 pub struct TumblrResponse<T> {
     pub meta: TumblrResponseMeta,
@@ -80,7 +121,12 @@ pub struct TumblrResponse<T> {
 pub trait TumblrRequest: Sized {
     type Response: DeserializeOwned;
 
-    fn build_request(&self, client: &TumblrClient) -> Result<Request, Box<dyn std::error::Error>>;
+    fn build_request(&self, client: &TumblrClient) -> Result<Request>;
+
+    /// Whether the request can be built again to retry it.
+    fn is_replayable(&self) -> bool {
+        true
+    }
 
     fn deserialize_response(
         &self,
@@ -91,32 +137,154 @@ pub trait TumblrRequest: Sized {
 }
 
 impl TumblrClient {
-    pub async fn send_request<R>(
-        &mut self,
-        request: &R,
-    ) -> Result<TumblrResponse<<R>::Response>, Box<dyn std::error::Error>>
+    pub async fn send_request<R>(&self, request: &R) -> Result<TumblrResponse<<R>::Response>>
     where
         R: TumblrRequest,
     {
         let response_raw = self.send_request_raw(request).await?;
-        Ok(request.deserialize_response(&response_raw)?)
+        request
+            .deserialize_response(&response_raw)
+            .context(DecodeSnafu { body: response_raw })
     }
 
-    pub async fn send_request_raw<R>(
-        &mut self,
-        request: &R,
-    ) -> Result<String, Box<dyn std::error::Error>>
+    pub async fn send_request_raw<R>(&self, request: &R) -> Result<String>
     where
         R: TumblrRequest,
     {
-        // Refresh token if expired
-        self.refresh_token_if_expired().await?;
+        // Refresh token if expired, a replayed cassette doesn't need a valid one
+        if !self.is_replaying() {
+            self.refresh_token_if_expired().await.context(AuthSnafu)?;
+        }
+
+        // A request whose body can't be rebuilt fails with its first error.
+        let replayable = request.is_replayable();
+        let mut attempt = 0;
+        loop {
+            let built = request.build_request(self)?;
+            let method = built.method().clone();
+            let (status, headers, body) = match self.execute(built).await {
+                Ok(response) => response,
+                Err(Error::Http { source }) => {
+                    let delay = self.retry_policy.delay_for_error(&method, &source, attempt);
+                    match delay.filter(|_| replayable) {
+                        Some(delay) => {
+                            tokio::time::sleep(delay).await;
+                            attempt += 1;
+                            continue;
+                        }
+                        None => return Err(source).context(HttpSnafu),
+                    }
+                }
+                Err(error) => return Err(error),
+            };
+
+            let rate_limit = RateLimit::from_headers(&headers);
+            let delay = self
+                .retry_policy
+                .delay_for(&method, status, &rate_limit, attempt)
+                .filter(|_| replayable);
+            if !rate_limit.is_empty() {
+                self.set_rate_limit(rate_limit);
+            }
 
-        Ok(self
+            if status.is_success() {
+                // Tumblr occasionally reports an error in the envelope of a 200.
+                return match envelope_status(&body) {
+                    Some(status) if !status.is_success() => {
+                        Err(ApiError::from_response(status, body)).context(ApiSnafu)
+                    }
+                    _ => Ok(body),
+                };
+            }
+            match delay {
+                Some(delay) => {
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                None => return Err(ApiError::from_response(status, body)).context(ApiSnafu),
+            }
+        }
+    }
+
+    /// Sends a request, or answers it from the cassette when one is attached.
+    async fn execute(&self, request: Request) -> Result<(StatusCode, HeaderMap, String)> {
+        #[cfg(feature = "testing")]
+        let key = match self.cassette_mode() {
+            Some(CassetteMode::Replay) => {
+                let (method, path, body) = Cassette::key(&request);
+                return self
+                    .cassette()
+                    .as_mut()
+                    .and_then(|cassette| cassette.replay_request(&method, &path, body.as_deref()))
+                    .context(CassetteMissSnafu { method, path });
+            }
+            Some(CassetteMode::Record) => Some(Cassette::key(&request)),
+            None => None,
+        };
+
+        let response = self
             .request_client
-            .execute(request.build_request(self)?)
-            .await?
-            .text()
-            .await?)
+            .execute(request)
+            .await
+            .context(HttpSnafu)?;
+        let (status, headers) = (response.status(), response.headers().clone());
+        let body = response.text().await.context(HttpSnafu)?;
+
+        #[cfg(feature = "testing")]
+        if let (Some(key), Some(cassette)) = (key, self.cassette().as_mut()) {
+            cassette.record_response(key, status, &headers, &body);
+        }
+        Ok((status, headers, body))
+    }
+
+    #[cfg(feature = "testing")]
+    fn is_replaying(&self) -> bool {
+        self.cassette_mode() == Some(CassetteMode::Replay)
+    }
+
+    #[cfg(not(feature = "testing"))]
+    fn is_replaying(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+    use reqwest::StatusCode;
+    use serde_json::json;
+
+    use crate::{
+        blog::TumblrBlogId,
+        requests::blog::blog::BlogInfoRequest,
+        testing::{MockResponse, MockTumblr},
+        Error,
+    };
+
+    #[tokio::test]
+    async fn errors_in_a_successful_envelope_are_errors() {
+        let mock = MockTumblr::start().await.unwrap();
+        mock.add_blog("staff");
+        mock.respond_next(MockResponse::raw_json(
+            StatusCode::OK,
+            json!({
+                "meta": { "status": 404, "msg": "Not Found" },
+                "response": [],
+                "errors": [{ "title": "Not Found", "code": 0, "detail": "Blog not found." }],
+            }),
+        ));
+
+        let error = mock
+            .client()
+            .send_request(&BlogInfoRequest {
+                blog_id: TumblrBlogId::BlogName("staff".to_string()),
+            })
+            .await
+            .unwrap_err();
+        let Error::Api { source } = error else {
+            panic!("Expected an API error, got {error:?}.");
+        };
+        assert_eq!(source.status, StatusCode::NOT_FOUND);
+        assert_eq!(source.msg, "Not Found");
+        assert_eq!(mock.requests().len(), 1);
     }
 }
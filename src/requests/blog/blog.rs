@@ -1,8 +1,10 @@
 use reqwest::Request;
 use serde::Deserialize;
+use snafu::prelude::*;
 
 use crate::{
     blog::{TumblrBlog, TumblrBlogId},
+    error::{Result, UrlBuildSnafu},
     requests::{paths, HttpMethod, TumblrRequest, TumblrRequestBuilder},
     TumblrClient,
 };
@@ -15,14 +17,14 @@ pub struct BlogInfoRequest {
 impl TumblrRequest for BlogInfoRequest {
     type Response = BlogInfoResponse;
 
-    fn build_request(&self, client: &TumblrClient) -> Result<Request, Box<dyn std::error::Error>> {
-        Ok(TumblrRequestBuilder::new(
+    fn build_request(&self, client: &TumblrClient) -> Result<Request> {
+        TumblrRequestBuilder::new(
             &client.request_client,
             HttpMethod::Get,
-            paths::blog_info(self.blog_id.clone().to_string())?,
+            paths::blog_info(client.api_base(), &self.blog_id).context(UrlBuildSnafu)?,
         )?
         .auth_by_key(client.get_api_key())
-        .build()?)
+        .build()
     }
 }
 
@@ -1,9 +1,12 @@
 use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+use snafu::prelude::*;
 
 use crate::{
     blog::TumblrBlogId,
+    error::{EncodeSnafu, Result, UrlBuildSnafu},
     post::{PostCreate, PostGet},
-    requests::{paths, HttpMethod, TumblrRequest, TumblrRequestBuilder},
+    requests::{media::MediaAttachment, paths, HttpMethod, TumblrRequest, TumblrRequestBuilder},
     TumblrClient,
 };
 
@@ -11,23 +14,29 @@ use crate::{
 pub struct PostCreateRequest {
     pub blog_id: TumblrBlogId,
     pub parameters: PostCreate,
+    /// Files referenced by `identifier` in the post's media objects.
+    pub media: Vec<MediaAttachment>,
 }
 
 impl TumblrRequest for PostCreateRequest {
     type Response = PostCreateResponse;
 
-    fn build_request(
-        &self,
-        client: &TumblrClient,
-    ) -> Result<reqwest::Request, Box<dyn std::error::Error>> {
-        Ok(TumblrRequestBuilder::new(
+    fn build_request(&self, client: &TumblrClient) -> Result<reqwest::Request> {
+        TumblrRequestBuilder::new(
             &client.request_client,
             HttpMethod::Post,
-            paths::blog_post_create(self.blog_id.clone().to_string())?,
+            paths::blog_post_create(client.api_base(), &self.blog_id).context(UrlBuildSnafu)?,
         )?
-        .auth_by_oauth(client.get_access_token())
-        .json(serde_json::to_string(&self.parameters)?)
-        .build()?)
+        .auth_by_oauth(&client.get_access_token()?)
+        .json_with_media(
+            serde_json::to_string(&self.parameters).context(EncodeSnafu)?,
+            &self.media,
+        )?
+        .build()
+    }
+
+    fn is_replayable(&self) -> bool {
+        self.media.iter().all(MediaAttachment::is_replayable)
     }
 }
 
@@ -36,6 +45,43 @@ pub struct PostCreateResponse {
     pub id: String,
 }
 
+#[derive(Debug)]
+pub struct PostEditRequest {
+    pub blog_id: TumblrBlogId,
+    pub post_id: String,
+    pub parameters: PostCreate,
+    /// Files referenced by `identifier` in the post's media objects.
+    pub media: Vec<MediaAttachment>,
+}
+
+impl TumblrRequest for PostEditRequest {
+    type Response = PostEditResponse;
+
+    fn build_request(&self, client: &TumblrClient) -> Result<reqwest::Request> {
+        TumblrRequestBuilder::new(
+            &client.request_client,
+            HttpMethod::Put,
+            paths::blog_post(client.api_base(), &self.blog_id, &self.post_id)
+                .context(UrlBuildSnafu)?,
+        )?
+        .auth_by_oauth(&client.get_access_token()?)
+        .json_with_media(
+            serde_json::to_string(&self.parameters).context(EncodeSnafu)?,
+            &self.media,
+        )?
+        .build()
+    }
+
+    fn is_replayable(&self) -> bool {
+        self.media.iter().all(MediaAttachment::is_replayable)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PostEditResponse {
+    pub id: String,
+}
+
 #[derive(Debug, Serialize)]
 pub struct PostGetRequest {
     pub blog_id: TumblrBlogId,
@@ -45,17 +91,15 @@ pub struct PostGetRequest {
 impl TumblrRequest for PostGetRequest {
     type Response = PostGetResponse;
 
-    fn build_request(
-        &self,
-        client: &TumblrClient,
-    ) -> Result<reqwest::Request, Box<dyn std::error::Error>> {
-        Ok(TumblrRequestBuilder::new(
+    fn build_request(&self, client: &TumblrClient) -> Result<reqwest::Request> {
+        TumblrRequestBuilder::new(
             &client.request_client,
             HttpMethod::Get,
-            paths::blog_post(self.blog_id.clone().to_string(), self.post_id.clone())?,
+            paths::blog_post(client.api_base(), &self.blog_id, &self.post_id)
+                .context(UrlBuildSnafu)?,
         )?
-        .auth_by_oauth(client.get_access_token())
-        .build()?)
+        .auth_by_oauth(&client.get_access_token()?)
+        .build()
     }
 }
 
@@ -64,3 +108,87 @@ pub struct PostGetResponse {
     #[serde(flatten)]
     pub parameters: PostGet,
 }
+
+/// Lists a blog's published posts, which only needs the API key.
+#[skip_serializing_none]
+#[derive(Debug, Serialize)]
+pub struct PostSearchRequest {
+    #[serde(skip)]
+    pub blog_id: TumblrBlogId,
+    pub offset: Option<u32>,
+    pub npf: Option<bool>,
+}
+
+impl PostSearchRequest {
+    pub fn new(blog_id: TumblrBlogId) -> Self {
+        Self {
+            blog_id,
+            offset: None,
+            npf: Some(true),
+        }
+    }
+}
+
+impl TumblrRequest for PostSearchRequest {
+    type Response = PostSearchResponse;
+
+    fn build_request(&self, client: &TumblrClient) -> Result<reqwest::Request> {
+        TumblrRequestBuilder::new(
+            &client.request_client,
+            HttpMethod::Get,
+            paths::blog_posts_search(client.api_base(), &self.blog_id).context(UrlBuildSnafu)?,
+        )?
+        .auth_by_key(client.get_api_key())
+        .query(self)
+        .build()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PostSearchResponse {
+    pub total_posts: u32,
+    pub posts: Vec<PostGet>,
+}
+
+/// Lists asks and submissions waiting in a blog's inbox, which can be answered with
+/// [`PostGet::answer`].
+#[skip_serializing_none]
+#[derive(Debug, Serialize)]
+pub struct PostSubmissionsRequest {
+    #[serde(skip)]
+    pub blog_id: TumblrBlogId,
+    pub offset: Option<u32>,
+    /// Returns posts in Neue Post Format, which is needed to answer them.
+    pub npf: Option<bool>,
+}
+
+impl PostSubmissionsRequest {
+    pub fn new(blog_id: TumblrBlogId) -> Self {
+        Self {
+            blog_id,
+            offset: None,
+            npf: Some(true),
+        }
+    }
+}
+
+impl TumblrRequest for PostSubmissionsRequest {
+    type Response = PostSubmissionsResponse;
+
+    fn build_request(&self, client: &TumblrClient) -> Result<reqwest::Request> {
+        TumblrRequestBuilder::new(
+            &client.request_client,
+            HttpMethod::Get,
+            paths::blog_post_submission_get(client.api_base(), &self.blog_id)
+                .context(UrlBuildSnafu)?,
+        )?
+        .auth_by_oauth(&client.get_access_token()?)
+        .query(self)
+        .build()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PostSubmissionsResponse {
+    pub posts: Vec<PostGet>,
+}
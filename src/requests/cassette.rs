@@ -0,0 +1,425 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use reqwest::{header::HeaderMap, Request, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+const REDACTED: &str = "REDACTED";
+/// Query parameters, request and response fields holding secrets.
+const SECRET_FIELDS: [&str; 7] = [
+    "api_key",
+    "access_token",
+    "refresh_token",
+    "client_id",
+    "client_secret",
+    "code",
+    "code_verifier",
+];
+
+/// A request and the response Tumblr gave to it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Interaction {
+    pub method: String,
+    /// The path and query, without the host and with secrets redacted.
+    pub path: String,
+    /// `None` for streamed bodies, such as media uploads, which are matched on any body.
+    pub body: Option<String>,
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub response: String,
+}
+
+impl Interaction {
+    fn matches(&self, method: &str, path: &str, body: Option<&str>) -> bool {
+        self.method == method
+            && self.path == path
+            && match (&self.body, body) {
+                (Some(recorded), Some(body)) => same_body(recorded, body),
+                _ => true,
+            }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CassetteMode {
+    /// Requests go to Tumblr and every exchange is written to the cassette.
+    Record,
+    /// Responses come from the cassette, nothing is sent.
+    Replay,
+}
+
+/// Recorded exchanges with Tumblr, for running tests offline.
+///
+/// Attach one to a client with
+/// [`TumblrClientBuilder::cassette`](crate::auth::TumblrClientBuilder::cassette). API keys and
+/// tokens are redacted before anything is written, request headers aren't kept at all.
+///
+/// Recorded exchanges are kept in memory until [`Cassette::flush`] or
+/// [`TumblrClient::flush_cassette`](crate::TumblrClient::flush_cassette), and are flushed when
+/// the cassette is dropped.
+///
+/// ```
+/// # use chrono::Utc;
+/// # use oauth2::AccessToken;
+/// # use tumblr_api::{
+/// #     blog::TumblrBlogId, endpoints::Endpoints,
+/// #     requests::{blog::posts::PostGetRequest, cassette::Cassette}, testing::MockTumblr,
+/// #     TumblrClient,
+/// # };
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let path = std::env::temp_dir().join("tumblr-api-cassette.json");
+/// let mock = MockTumblr::start().await?;
+/// mock.add_blog("staff");
+/// let request = PostGetRequest {
+///     blog_id: TumblrBlogId::BlogName("staff".to_string()),
+///     post_id: "404".to_string(),
+/// };
+///
+/// let recording = mock.client().with_cassette(Cassette::record(&path));
+/// let recorded = recording.send_request(&request).await.unwrap_err();
+/// recording.flush_cassette()?;
+///
+/// // Nothing listens here, the response comes from the cassette.
+/// let replaying = TumblrClient::builder(mock.credentials())
+///     .endpoints(Endpoints::local(&"http://127.0.0.1:9/".parse()?)?)
+///     .cassette(Cassette::replay(&path)?)
+///     .from_tokens(AccessToken::new("expired".into()), None, Utc::now());
+/// let replayed = replaying.send_request(&request).await.unwrap_err();
+/// assert_eq!(recorded.to_string(), replayed.to_string());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Cassette {
+    path: PathBuf,
+    mode: CassetteMode,
+    interactions: Vec<Interaction>,
+    /// Which interactions have been replayed, each is only used once.
+    used: Vec<bool>,
+    /// Whether there are recorded interactions that haven't been written yet.
+    unsaved: bool,
+}
+
+impl Cassette {
+    /// Starts an empty cassette, replacing the file once the first exchange is flushed.
+    pub fn record(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            mode: CassetteMode::Record,
+            interactions: Vec::new(),
+            used: Vec::new(),
+            unsaved: false,
+        }
+    }
+
+    pub fn replay(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        let interactions: Vec<Interaction> = serde_json::from_str(&fs::read_to_string(&path)?)?;
+        Ok(Self {
+            path,
+            mode: CassetteMode::Replay,
+            used: vec![false; interactions.len()],
+            interactions,
+            unsaved: false,
+        })
+    }
+
+    pub fn mode(&self) -> CassetteMode {
+        self.mode
+    }
+
+    pub fn interactions(&self) -> &[Interaction] {
+        &self.interactions
+    }
+
+    /// Writes the recorded exchanges out, if any were recorded since the last flush.
+    pub fn flush(&mut self) -> io::Result<()> {
+        if !self.unsaved {
+            return Ok(());
+        }
+        let mut file = io::BufWriter::new(fs::File::create(&self.path)?);
+        serde_json::to_writer_pretty(&mut file, &self.interactions)?;
+        file.flush()?;
+        self.unsaved = false;
+        Ok(())
+    }
+
+    /// The method, redacted path and body a request is recorded and matched by.
+    pub(crate) fn key(request: &Request) -> (String, String, Option<String>) {
+        let url = request.url();
+        let mut path = url.path().to_string();
+        if url.query().is_some() {
+            let query = url
+                .query_pairs()
+                .map(|(name, value)| {
+                    let value = if is_secret(&name) {
+                        REDACTED.into()
+                    } else {
+                        value
+                    };
+                    format!("{name}={value}")
+                })
+                .collect::<Vec<_>>()
+                .join("&");
+            path = format!("{path}?{query}");
+        }
+        let body = request
+            .body()
+            .and_then(|body| body.as_bytes())
+            .map(|bytes| redact_request_body(&String::from_utf8_lossy(bytes)));
+        (request.method().to_string(), path, body)
+    }
+
+    /// The first unused interaction matching the request.
+    pub(crate) fn replay_request(
+        &mut self,
+        method: &str,
+        path: &str,
+        body: Option<&str>,
+    ) -> Option<(StatusCode, HeaderMap, String)> {
+        let index = self
+            .interactions
+            .iter()
+            .zip(&self.used)
+            .position(|(interaction, used)| !used && interaction.matches(method, path, body))?;
+        self.used[index] = true;
+
+        let interaction = &self.interactions[index];
+        let mut headers = HeaderMap::new();
+        for (name, value) in &interaction.headers {
+            if let (Ok(name), Ok(value)) =
+                (name.parse::<reqwest::header::HeaderName>(), value.parse())
+            {
+                headers.append(name, value);
+            }
+        }
+        Some((
+            StatusCode::from_u16(interaction.status).unwrap_or(StatusCode::OK),
+            headers,
+            interaction.response.clone(),
+        ))
+    }
+
+    /// Adds an exchange, to be written on the next flush.
+    pub(crate) fn record_response(
+        &mut self,
+        (method, path, body): (String, String, Option<String>),
+        status: StatusCode,
+        headers: &HeaderMap,
+        response: &str,
+    ) {
+        self.interactions.push(Interaction {
+            method,
+            path,
+            body,
+            status: status.as_u16(),
+            headers: headers
+                .iter()
+                .filter(|(name, _)| {
+                    let name = name.as_str();
+                    name.starts_with("x-ratelimit") || name == "retry-after"
+                })
+                .filter_map(|(name, value)| {
+                    Some((name.to_string(), value.to_str().ok()?.to_string()))
+                })
+                .collect(),
+            response: redact_body(response),
+        });
+        self.used.push(true);
+        self.unsaved = true;
+    }
+}
+
+impl Drop for Cassette {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+/// Bodies are compared as JSON when they are JSON, so formatting doesn't matter.
+fn same_body(recorded: &str, body: &str) -> bool {
+    match (
+        serde_json::from_str::<Value>(recorded),
+        serde_json::from_str::<Value>(body),
+    ) {
+        (Ok(recorded), Ok(body)) => recorded == body,
+        _ => recorded == body,
+    }
+}
+
+fn redact_body(body: &str) -> String {
+    match serde_json::from_str::<Value>(body) {
+        Ok(mut value) => {
+            redact_value(&mut value);
+            value.to_string()
+        }
+        Err(_) => body.to_string(),
+    }
+}
+
+/// Redacts JSON bodies like responses, and form bodies such as token requests.
+fn redact_request_body(body: &str) -> String {
+    if serde_json::from_str::<Value>(body).is_ok() {
+        return redact_body(body);
+    }
+    match serde_urlencoded::from_str::<Vec<(String, String)>>(body) {
+        Ok(fields) if fields.iter().any(|(name, _)| is_secret(name)) => {
+            let fields = fields
+                .into_iter()
+                .map(|(name, value)| {
+                    let value = if is_secret(&name) {
+                        REDACTED.to_string()
+                    } else {
+                        value
+                    };
+                    (name, value)
+                })
+                .collect::<Vec<_>>();
+            serde_urlencoded::to_string(fields).unwrap_or_else(|_| body.to_string())
+        }
+        _ => body.to_string(),
+    }
+}
+
+fn is_secret(name: &str) -> bool {
+    SECRET_FIELDS.contains(&name)
+}
+
+fn redact_value(value: &mut Value) {
+    match value {
+        Value::Object(object) => {
+            for (name, value) in object.iter_mut() {
+                if is_secret(name) && value.is_string() {
+                    *value = Value::String(REDACTED.to_string());
+                } else {
+                    redact_value(value);
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(redact_value),
+        _ => (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, process};
+
+    use chrono::Utc;
+    use oauth2::AccessToken;
+
+    use super::*;
+    use crate::{
+        blog::TumblrBlogId,
+        endpoints::Endpoints,
+        post::PostBuilder,
+        requests::blog::posts::{PostGetRequest, PostSearchRequest},
+        testing::MockTumblr,
+        Error, TumblrClient,
+    };
+
+    fn temp_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("tumblr-api-{name}-{}.json", process::id()))
+    }
+
+    fn staff() -> TumblrBlogId {
+        TumblrBlogId::BlogName("staff".to_string())
+    }
+
+    /// A client that fails to connect, so anything it gets came from the cassette.
+    fn replaying(mock: &MockTumblr, cassette: Cassette) -> TumblrClient {
+        TumblrClient::builder(mock.credentials())
+            .endpoints(Endpoints::local(&"http://127.0.0.1:9/".parse().unwrap()).unwrap())
+            .cassette(cassette)
+            .from_tokens(AccessToken::new("expired".into()), None, Utc::now())
+    }
+
+    #[tokio::test]
+    async fn replays_what_was_recorded() {
+        let path = temp_path("record-replay");
+        let mock = MockTumblr::start().await.unwrap();
+        mock.add_blog("staff");
+        let create = PostBuilder::new()
+            .paragraph(|p| p.text("Hello!"))
+            .build_request(staff());
+
+        let recording = mock.client().with_cassette(Cassette::record(&path));
+        let id = recording.send_request(&create).await.unwrap().response.id;
+        let get = PostGetRequest {
+            blog_id: staff(),
+            post_id: id.clone(),
+        };
+        recording.send_request(&get).await.unwrap();
+        recording
+            .send_request(&PostSearchRequest::new(staff()))
+            .await
+            .unwrap();
+        assert!(!path.exists(), "Recorded before being flushed.");
+        recording.flush_cassette().unwrap();
+
+        let recorded = fs::read_to_string(&path).unwrap();
+        let token = recording.get_access_token().unwrap();
+        assert!(!recorded.contains(token.secret().as_str()));
+        assert!(!recorded.contains(&mock.credentials().consumer_key));
+        assert!(recorded.contains("api_key=REDACTED"));
+
+        let replaying = replaying(&mock, Cassette::replay(&path).unwrap());
+        fs::remove_file(&path).unwrap();
+        assert_eq!(
+            replaying.send_request(&create).await.unwrap().response.id,
+            id
+        );
+        let post = replaying.send_request(&get).await.unwrap().response;
+        assert_eq!(post.parameters.to_plain_text(), "Hello!");
+        assert_eq!(mock.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn misses_are_errors() {
+        let mock = MockTumblr::start().await.unwrap();
+        let mut cassette = Cassette::record(temp_path("unused"));
+        cassette.mode = CassetteMode::Replay;
+        let error = replaying(&mock, cassette)
+            .send_request(&PostGetRequest {
+                blog_id: staff(),
+                post_id: "1".to_string(),
+            })
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(
+                &error,
+                Error::CassetteMiss { method, path }
+                    if method == "GET" && path == "/v2/blog/staff/posts/1"
+            ),
+            "{error:?}"
+        );
+        assert!(mock.requests().is_empty());
+    }
+
+    #[test]
+    fn redacts_request_bodies() {
+        let request = reqwest::Client::new()
+            .post("https://api.tumblr.com/v2/oauth2/token")
+            .body("grant_type=authorization_code&code=abc&client_secret=shh")
+            .build()
+            .unwrap();
+        let (_, _, body) = Cassette::key(&request);
+        assert_eq!(
+            body.as_deref(),
+            Some("grant_type=authorization_code&code=REDACTED&client_secret=REDACTED")
+        );
+
+        let json = r#"{"refresh_token":"abc","content":[{"text":"hi"}]}"#;
+        assert_eq!(
+            redact_request_body(json),
+            r#"{"content":[{"text":"hi"}],"refresh_token":"REDACTED"}"#
+        );
+        assert_eq!(redact_request_body("not=secret"), "not=secret");
+    }
+}
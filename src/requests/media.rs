@@ -0,0 +1,258 @@
+use std::{
+    fmt,
+    future::Future,
+    io,
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::Mutex,
+    task::{ready, Context, Poll},
+};
+
+use reqwest::{
+    multipart::{Form, Part},
+    Body,
+};
+use snafu::prelude::*;
+use tokio::{
+    fs::File,
+    io::{AsyncRead, ReadBuf},
+};
+use tokio_util::io::ReaderStream;
+
+use super::JSON_HEADER_VALUE;
+use crate::error::{HttpSnafu, MediaSnafu, Result};
+
+const JSON_PART_NAME: &str = "json";
+
+type BoxedReader = Box<dyn AsyncRead + Send + Sync + Unpin>;
+
+/// Where the bytes of an attached file come from.
+pub enum MediaSource {
+    /// Streamed from disk when the request is sent, a missing file fails the request with
+    /// [`Error::Http`](crate::Error::Http).
+    Path(PathBuf),
+    Bytes(Vec<u8>),
+    /// Can only be sent once, the reader is consumed by the first request built.
+    Reader(Mutex<Option<BoxedReader>>),
+}
+
+impl fmt::Debug for MediaSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Path(path) => f.debug_tuple("Path").field(path).finish(),
+            Self::Bytes(bytes) => f
+                .debug_tuple("Bytes")
+                .field(&format_args!("{} bytes", bytes.len()))
+                .finish(),
+            Self::Reader(_) => f.debug_tuple("Reader").finish_non_exhaustive(),
+        }
+    }
+}
+
+/// A file uploaded alongside a post.
+///
+/// The `identifier` must match the `identifier` of a [`MediaObject`](crate::post::MediaObject)
+/// in the post's content.
+///
+/// https://www.tumblr.com/docs/npf#media-objects
+#[derive(Debug)]
+pub struct MediaAttachment {
+    pub identifier: String,
+    pub file_name: Option<String>,
+    pub mime_type: Option<String>,
+    pub source: MediaSource,
+}
+
+impl MediaAttachment {
+    /// The file name and mime type are guessed from the path.
+    pub fn from_path(identifier: impl Into<String>, path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        Self {
+            identifier: identifier.into(),
+            file_name: path
+                .file_name()
+                .map(|file_name| file_name.to_string_lossy().into_owned()),
+            mime_type: mime_guess::from_path(path)
+                .first()
+                .map(|mime| mime.to_string()),
+            source: MediaSource::Path(path.to_path_buf()),
+        }
+    }
+
+    pub fn from_bytes(
+        identifier: impl Into<String>,
+        bytes: impl Into<Vec<u8>>,
+        mime_type: impl Into<String>,
+    ) -> Self {
+        Self {
+            identifier: identifier.into(),
+            file_name: None,
+            mime_type: Some(mime_type.into()),
+            source: MediaSource::Bytes(bytes.into()),
+        }
+    }
+
+    pub fn from_reader(
+        identifier: impl Into<String>,
+        reader: impl AsyncRead + Send + Sync + Unpin + 'static,
+        mime_type: impl Into<String>,
+    ) -> Self {
+        Self {
+            identifier: identifier.into(),
+            file_name: None,
+            mime_type: Some(mime_type.into()),
+            source: MediaSource::Reader(Mutex::new(Some(Box::new(reader)))),
+        }
+    }
+
+    /// Whether the attachment can be sent again, a reader can only be read once.
+    pub fn is_replayable(&self) -> bool {
+        !matches!(self.source, MediaSource::Reader(_))
+    }
+
+    pub fn with_file_name(mut self, file_name: impl Into<String>) -> Self {
+        self.file_name = Some(file_name.into());
+        self
+    }
+
+    fn to_part(&self) -> Result<Part> {
+        let body = match &self.source {
+            MediaSource::Path(path) => Body::wrap_stream(ReaderStream::new(LazyFile::new(path))),
+            MediaSource::Bytes(bytes) => Body::from(bytes.clone()),
+            MediaSource::Reader(reader) => {
+                let reader = reader
+                    .lock()
+                    .map_err(|_| io::Error::other("Media reader poisoned."))
+                    .context(MediaSnafu)?
+                    .take()
+                    .ok_or_else(|| io::Error::other("Media reader was already consumed."))
+                    .context(MediaSnafu)?;
+                Body::wrap_stream(ReaderStream::new(reader))
+            }
+        };
+
+        // Tumblr needs a file name to treat the part as an upload.
+        let mut part = Part::stream(body).file_name(
+            self.file_name
+                .clone()
+                .unwrap_or_else(|| self.identifier.clone()),
+        );
+        if let Some(mime_type) = &self.mime_type {
+            part = part.mime_str(mime_type).context(HttpSnafu)?;
+        }
+        Ok(part)
+    }
+}
+
+type OpenFile = Pin<Box<dyn Future<Output = io::Result<File>> + Send + Sync>>;
+
+/// Opens the file when the body is first read, so building a request doesn't block on disk.
+enum LazyFile {
+    Opening(OpenFile),
+    Open(File),
+}
+
+impl LazyFile {
+    fn new(path: &Path) -> Self {
+        Self::Opening(Box::pin(File::open(path.to_path_buf())))
+    }
+}
+
+impl AsyncRead for LazyFile {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        loop {
+            match &mut *self {
+                Self::Opening(open) => {
+                    let file = ready!(open.as_mut().poll(cx))?;
+                    *self = Self::Open(file);
+                }
+                Self::Open(file) => return Pin::new(file).poll_read(cx, buf),
+            }
+        }
+    }
+}
+
+/// Builds a `multipart/form-data` body with the post's `json` and a part per attachment.
+pub fn create_form(json: String, media: &[MediaAttachment]) -> Result<Form> {
+    let mut form = Form::new().part(
+        JSON_PART_NAME,
+        Part::text(json)
+            .mime_str(JSON_HEADER_VALUE)
+            .context(HttpSnafu)?,
+    );
+    for attachment in media {
+        form = form.part(attachment.identifier.clone(), attachment.to_part()?);
+    }
+    Ok(form)
+}
+
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+    use std::{env, fs, process};
+
+    use super::*;
+    use crate::{blog::TumblrBlogId, post::PostBuilder, testing::MockTumblr, Error};
+
+    fn staff() -> TumblrBlogId {
+        TumblrBlogId::BlogName("staff".to_string())
+    }
+
+    #[tokio::test]
+    async fn sends_a_part_per_attachment() {
+        let path = env::temp_dir().join(format!("tumblr-api-upload-{}.png", process::id()));
+        fs::write(&path, b"from disk").unwrap();
+        let mock = MockTumblr::start().await.unwrap();
+        mock.add_blog("staff");
+
+        let request = PostBuilder::new()
+            .image_upload(MediaAttachment::from_path("disk", &path), None)
+            .image_upload(
+                MediaAttachment::from_bytes("memory", b"from memory".to_vec(), "image/gif"),
+                None,
+            )
+            .image_upload(
+                MediaAttachment::from_reader("reader", &b"from a reader"[..], "image/jpeg"),
+                None,
+            )
+            .build_request(staff());
+        mock.client().send_request(&request).await.unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let sent = mock.requests().pop().unwrap();
+        let identifiers = sent.json().unwrap()["content"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|block| {
+                block["media"][0]["identifier"]
+                    .as_str()
+                    .unwrap()
+                    .to_string()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(identifiers, ["disk", "memory", "reader"]);
+        assert_eq!(sent.part("disk"), Some(&b"from disk"[..]));
+        assert_eq!(sent.part("memory"), Some(&b"from memory"[..]));
+        assert_eq!(sent.part("reader"), Some(&b"from a reader"[..]));
+        assert_eq!(mock.posts("staff").len(), 1);
+    }
+
+    #[tokio::test]
+    async fn missing_files_fail_the_request() {
+        let mock = MockTumblr::start().await.unwrap();
+        mock.add_blog("staff");
+        let request = PostBuilder::new()
+            .image_upload(
+                MediaAttachment::from_path("missing", "/nonexistent/tumblr-api.png"),
+                None,
+            )
+            .build_request(staff());
+        let error = mock.client().send_request(&request).await.unwrap_err();
+        assert!(matches!(error, Error::Http { .. }), "{error:?}");
+        assert!(mock.posts("staff").is_empty());
+    }
+}
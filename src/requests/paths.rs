@@ -6,8 +6,8 @@ macro_rules! paths {
     ) => {
         $(
             $(#[$attr])*
-            pub fn $name($($argument: impl core::fmt::Display),*) -> Result<reqwest::Url, oauth2::url::ParseError> {
-                reqwest::Url::parse(&format!(concat!("https://api.tumblr.com/v2/", $path_prefix, $path), $($argument),*))
+            pub fn $name(api_base: &reqwest::Url, $($argument: impl core::fmt::Display),*) -> Result<reqwest::Url, oauth2::url::ParseError> {
+                api_base.join(&format!(concat!($path_prefix, $path), $($argument),*))
             }
         )*
     };
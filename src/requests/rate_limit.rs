@@ -0,0 +1,471 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use reqwest::{
+    header::{HeaderMap, RETRY_AFTER},
+    Method, StatusCode,
+};
+
+const PER_DAY_PREFIX: &str = "x-ratelimit-perday";
+const PER_HOUR_PREFIX: &str = "x-ratelimit-perhour";
+
+/// One of Tumblr's rate limit windows, as of the last response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimitWindow {
+    pub limit: u32,
+    pub remaining: u32,
+    pub reset_at: DateTime<Utc>,
+}
+
+impl RateLimitWindow {
+    fn from_headers(headers: &HeaderMap, prefix: &str) -> Option<Self> {
+        let header = |name: &str| {
+            headers
+                .get(format!("{prefix}-{name}"))?
+                .to_str()
+                .ok()?
+                .trim()
+                .parse::<u32>()
+                .ok()
+        };
+        Some(Self {
+            limit: header("limit")?,
+            remaining: header("remaining")?,
+            reset_at: Utc::now() + chrono::Duration::seconds(header("reset")?.into()),
+        })
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        self.remaining == 0
+    }
+
+    /// How long until the window resets, zero if it already has.
+    pub fn reset_in(&self) -> Duration {
+        (self.reset_at - Utc::now()).to_std().unwrap_or_default()
+    }
+}
+
+/// The rate limits Tumblr reported with a response.
+///
+/// https://www.tumblr.com/docs/en/api/v2#rate-limits
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RateLimit {
+    pub per_day: Option<RateLimitWindow>,
+    pub per_hour: Option<RateLimitWindow>,
+    /// How long Tumblr asked to wait before retrying.
+    pub retry_after: Option<Duration>,
+}
+
+impl RateLimit {
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        Self {
+            per_day: RateLimitWindow::from_headers(headers, PER_DAY_PREFIX),
+            per_hour: RateLimitWindow::from_headers(headers, PER_HOUR_PREFIX),
+            retry_after: headers
+                .get(RETRY_AFTER)
+                .and_then(|value| value.to_str().ok()?.trim().parse().ok())
+                .map(Duration::from_secs),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.per_day.is_none() && self.per_hour.is_none() && self.retry_after.is_none()
+    }
+
+    /// Requests left before the tightest window runs out.
+    pub fn remaining(&self) -> Option<u32> {
+        self.windows().map(|window| window.remaining).min()
+    }
+
+    /// How long until every exhausted window has reset, `None` if none are exhausted.
+    pub fn exhausted_for(&self) -> Option<Duration> {
+        self.windows()
+            .filter(|window| window.is_exhausted())
+            .map(RateLimitWindow::reset_in)
+            .max()
+    }
+
+    fn windows(&self) -> impl Iterator<Item = &RateLimitWindow> {
+        self.per_day.iter().chain(&self.per_hour)
+    }
+}
+
+/// How [`TumblrClient`](crate::TumblrClient) retries requests that were rate limited or hit
+/// a server error.
+///
+/// Rate limited requests were never acted on, so they are always retried. Server errors and
+/// timeouts only retry `GET` and other idempotent requests unless `retry_non_idempotent` is set,
+/// since Tumblr may have created or edited the post anyway.
+///
+/// Requests with media read from a [`MediaSource::Reader`](super::media::MediaSource::Reader)
+/// can't be sent twice, so they fail instead of retrying.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt, zero turns retrying off.
+    pub max_retries: u32,
+    /// The delay before the first retry, doubled for each one after it.
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    /// Randomizes each delay between half and all of it, so clients don't retry in lockstep.
+    pub jitter: bool,
+    /// Also retry 5xx responses and failed connections.
+    pub retry_server_errors: bool,
+    /// Also retry `POST` and `PUT` requests after a server error or timeout, which can publish
+    /// a post twice.
+    pub retry_non_idempotent: bool,
+    /// When a rate limit window is used up, waits for it to reset instead of backing off, as
+    /// long as that's within `max_wait`.
+    pub wait_for_reset: bool,
+    /// The longest a single wait can be, a longer `Retry-After` or reset fails the request.
+    pub max_wait: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            jitter: true,
+            retry_server_errors: true,
+            retry_non_idempotent: false,
+            wait_for_reset: false,
+            max_wait: Duration::from_secs(60 * 60),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Never retries.
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Default::default()
+        }
+    }
+
+    /// The exponential backoff before retry number `attempt`, counting from zero.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let delay = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay);
+        if self.jitter && !delay.is_zero() {
+            let half = delay / 2;
+            half + rand::thread_rng().gen_range(Duration::ZERO..=half)
+        } else {
+            delay
+        }
+    }
+
+    /// How long to wait before retrying a response, `None` if it shouldn't be retried.
+    pub fn delay_for(
+        &self,
+        method: &Method,
+        status: StatusCode,
+        rate_limit: &RateLimit,
+        attempt: u32,
+    ) -> Option<Duration> {
+        if attempt >= self.max_retries {
+            return None;
+        }
+
+        if status == StatusCode::TOO_MANY_REQUESTS {
+            let wait = rate_limit.retry_after.or_else(|| {
+                self.wait_for_reset
+                    .then(|| rate_limit.exhausted_for())
+                    .flatten()
+            });
+            match wait {
+                Some(wait) => (wait <= self.max_wait).then_some(wait),
+                None => Some(self.backoff(attempt)),
+            }
+        } else if status.is_server_error() && self.retry_server_errors && self.may_resend(method) {
+            Some(self.backoff(attempt))
+        } else {
+            None
+        }
+    }
+
+    /// How long to wait before retrying a request that failed to send.
+    pub fn delay_for_error(
+        &self,
+        method: &Method,
+        error: &reqwest::Error,
+        attempt: u32,
+    ) -> Option<Duration> {
+        // A failed connection never reached Tumblr, anything later might have.
+        let transient = error.is_connect()
+            || ((error.is_timeout() || error.is_request()) && self.may_resend(method));
+        (attempt < self.max_retries && self.retry_server_errors && transient)
+            .then(|| self.backoff(attempt))
+    }
+
+    fn may_resend(&self, method: &Method) -> bool {
+        method.is_idempotent() || self.retry_non_idempotent
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            jitter: false,
+            ..Default::default()
+        }
+    }
+
+    fn exhausted_hour(reset_in: i64) -> RateLimit {
+        RateLimit {
+            per_hour: Some(RateLimitWindow {
+                limit: 1000,
+                remaining: 0,
+                reset_at: Utc::now() + chrono::Duration::seconds(reset_in),
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_max() {
+        let policy = RetryPolicy {
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(5),
+            ..policy()
+        };
+        let delays = (0..4).map(|attempt| policy.backoff(attempt).as_secs());
+        assert_eq!(delays.collect::<Vec<_>>(), [1, 2, 4, 5]);
+
+        let jittered = RetryPolicy {
+            jitter: true,
+            ..policy
+        };
+        let delay = jittered.backoff(2);
+        assert!(delay >= Duration::from_secs(2) && delay <= Duration::from_secs(4));
+    }
+
+    #[test]
+    fn reads_rate_limit_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-ratelimit-perday-limit", "5000".parse().unwrap());
+        headers.insert("x-ratelimit-perday-remaining", "0".parse().unwrap());
+        headers.insert("x-ratelimit-perday-reset", "60".parse().unwrap());
+        headers.insert(RETRY_AFTER, "30".parse().unwrap());
+        let rate_limit = RateLimit::from_headers(&headers);
+
+        let per_day = rate_limit.per_day.unwrap();
+        assert_eq!((per_day.limit, per_day.remaining), (5000, 0));
+        assert!(rate_limit.per_hour.is_none());
+        assert_eq!(rate_limit.retry_after, Some(Duration::from_secs(30)));
+        assert_eq!(rate_limit.remaining(), Some(0));
+        assert!(rate_limit.exhausted_for().unwrap() <= Duration::from_secs(60));
+    }
+
+    #[test]
+    fn rate_limits_are_always_retried() {
+        let policy = policy();
+        let retry_after = RateLimit {
+            retry_after: Some(Duration::from_secs(3)),
+            ..Default::default()
+        };
+        assert_eq!(
+            policy.delay_for(
+                &Method::POST,
+                StatusCode::TOO_MANY_REQUESTS,
+                &retry_after,
+                0
+            ),
+            Some(Duration::from_secs(3))
+        );
+        assert_eq!(
+            policy.delay_for(
+                &Method::POST,
+                StatusCode::TOO_MANY_REQUESTS,
+                &retry_after,
+                3
+            ),
+            None
+        );
+
+        let too_long = RetryPolicy {
+            max_wait: Duration::from_secs(1),
+            ..policy
+        };
+        assert_eq!(
+            too_long.delay_for(&Method::GET, StatusCode::TOO_MANY_REQUESTS, &retry_after, 0),
+            None
+        );
+    }
+
+    #[test]
+    fn waits_for_reset_only_when_asked() {
+        let rate_limit = exhausted_hour(120);
+        let backoff = policy()
+            .delay_for(&Method::GET, StatusCode::TOO_MANY_REQUESTS, &rate_limit, 0)
+            .unwrap();
+        assert_eq!(backoff, Duration::from_millis(500));
+
+        let waiting = RetryPolicy {
+            wait_for_reset: true,
+            ..policy()
+        };
+        let wait = waiting
+            .delay_for(&Method::GET, StatusCode::TOO_MANY_REQUESTS, &rate_limit, 0)
+            .unwrap();
+        assert!(wait > Duration::from_secs(100) && wait <= Duration::from_secs(120));
+
+        let impatient = RetryPolicy {
+            max_wait: Duration::from_secs(60),
+            ..waiting
+        };
+        assert_eq!(
+            impatient.delay_for(&Method::GET, StatusCode::TOO_MANY_REQUESTS, &rate_limit, 0),
+            None
+        );
+    }
+
+    #[test]
+    fn server_errors_only_retry_idempotent_requests() {
+        let policy = policy();
+        let error = StatusCode::BAD_GATEWAY;
+        let none = RateLimit::default();
+        assert!(policy.delay_for(&Method::GET, error, &none, 0).is_some());
+        assert!(policy.delay_for(&Method::DELETE, error, &none, 0).is_some());
+        assert!(policy.delay_for(&Method::POST, error, &none, 0).is_none());
+        assert!(policy.delay_for(&Method::PUT, error, &none, 0).is_some());
+
+        let opted_in = RetryPolicy {
+            retry_non_idempotent: true,
+            ..policy
+        };
+        assert!(opted_in.delay_for(&Method::POST, error, &none, 0).is_some());
+        assert!(opted_in
+            .delay_for(&Method::GET, StatusCode::NOT_FOUND, &none, 0)
+            .is_none());
+    }
+
+    #[cfg(feature = "testing")]
+    mod mock {
+        use std::time::{Duration, Instant};
+
+        use reqwest::StatusCode;
+
+        use crate::{
+            blog::TumblrBlogId,
+            post::PostBuilder,
+            requests::{
+                blog::posts::PostCreateRequest, media::MediaAttachment, rate_limit::RetryPolicy,
+            },
+            testing::{MockResponse, MockTumblr},
+            Error,
+        };
+
+        fn staff() -> TumblrBlogId {
+            TumblrBlogId::BlogName("staff".to_string())
+        }
+
+        fn post() -> PostCreateRequest {
+            PostBuilder::new()
+                .paragraph(|p| p.text("Hello!"))
+                .build_request(staff())
+        }
+
+        fn api_status(error: Error) -> StatusCode {
+            match error {
+                Error::Api { source } => source.status,
+                error => panic!("Expected an API error, got {error:?}."),
+            }
+        }
+
+        async fn mock() -> MockTumblr {
+            let mock = MockTumblr::start().await.unwrap();
+            mock.add_blog("staff");
+            mock
+        }
+
+        #[tokio::test]
+        async fn rate_limited_posts_are_retried() {
+            let mock = mock().await;
+            mock.rate_limit_next(Duration::ZERO);
+            mock.client().send_request(&post()).await.unwrap();
+            assert_eq!(mock.requests().len(), 2);
+            assert_eq!(mock.posts("staff").len(), 1);
+        }
+
+        #[tokio::test]
+        async fn server_errors_on_posts_are_not_retried() {
+            let mock = mock().await;
+            mock.respond_next(MockResponse::error(
+                StatusCode::BAD_GATEWAY,
+                0,
+                "Bad Gateway",
+            ));
+            let error = mock.client().send_request(&post()).await.unwrap_err();
+            assert_eq!(api_status(error), StatusCode::BAD_GATEWAY);
+            assert_eq!(mock.requests().len(), 1);
+            assert!(mock.posts("staff").is_empty());
+        }
+
+        #[tokio::test]
+        async fn reader_uploads_are_never_retried() {
+            let mock = mock().await;
+            mock.rate_limit_next(Duration::ZERO);
+            let request = PostBuilder::new()
+                .image_upload(
+                    MediaAttachment::from_reader("photo", &b"not really a png"[..], "image/png"),
+                    None,
+                )
+                .build_request(staff());
+            let error = mock.client().send_request(&request).await.unwrap_err();
+            assert_eq!(api_status(error), StatusCode::TOO_MANY_REQUESTS);
+            assert_eq!(mock.requests().len(), 1);
+        }
+
+        #[tokio::test]
+        async fn rate_limit_is_read_from_responses() {
+            let mock = mock().await;
+            mock.set_daily_limit(Some(10));
+            let client = mock.client();
+            assert!(client.rate_limit().is_none());
+
+            client.send_request(&post()).await.unwrap();
+            let per_day = client.rate_limit().unwrap().per_day.unwrap();
+            assert_eq!((per_day.limit, per_day.remaining), (10, 9));
+        }
+
+        #[tokio::test]
+        async fn waits_for_the_exhausted_window_to_reset() {
+            let mock = mock().await;
+            mock.exhaust_hourly_limit_next(Duration::from_secs(1));
+            let client = mock.client().with_retry_policy(RetryPolicy {
+                wait_for_reset: true,
+                ..Default::default()
+            });
+
+            let start = Instant::now();
+            client.send_request(&post()).await.unwrap();
+            assert!(start.elapsed() >= Duration::from_millis(500));
+            assert_eq!(mock.requests().len(), 2);
+        }
+
+        #[tokio::test]
+        async fn gives_up_when_the_reset_is_too_far_off() {
+            let mock = mock().await;
+            mock.exhaust_hourly_limit_next(Duration::from_secs(3600));
+            let client = mock.client().with_retry_policy(RetryPolicy {
+                wait_for_reset: true,
+                max_wait: Duration::from_secs(60),
+                ..Default::default()
+            });
+
+            let error = client.send_request(&post()).await.unwrap_err();
+            assert_eq!(api_status(error), StatusCode::TOO_MANY_REQUESTS);
+            assert_eq!(mock.requests().len(), 1);
+            let per_hour = client.rate_limit().unwrap().per_hour.unwrap();
+            assert_eq!(per_hour.remaining, 0);
+        }
+    }
+}
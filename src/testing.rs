@@ -0,0 +1,332 @@
+mod http;
+mod state;
+
+use std::{
+    net::SocketAddr,
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
+};
+
+use chrono::Utc;
+use oauth2::{AccessToken, RefreshToken};
+use reqwest::{StatusCode, Url};
+use serde_json::Value;
+use tokio::{
+    io::{self, BufReader},
+    net::TcpListener,
+    task::JoinHandle,
+};
+
+use crate::{
+    auth::ConsumerCredentials, blog::TumblrUuid, endpoints::Endpoints,
+    requests::rate_limit::RetryPolicy, TumblrClient,
+};
+
+pub use self::http::{MockRequest, MockResponse};
+use self::state::MockState;
+
+/// An in-process stand-in for the Tumblr API, for tests that shouldn't touch the network.
+///
+/// Serves the OAuth token endpoint, blog info, creating, editing, fetching and deleting
+/// posts, the queue, drafts and submissions, likes and follows, all from memory. Responses
+/// use Tumblr's envelopes, errors included. The server stops when this is dropped.
+///
+/// ```
+/// # use tumblr_api::{
+/// #     blog::TumblrBlogId, post::PostBuilder, requests::blog::posts::PostGetRequest,
+/// #     testing::MockTumblr,
+/// # };
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mock = MockTumblr::start().await?;
+/// mock.add_blog("staff");
+/// let client = mock.client();
+///
+/// let request = PostBuilder::new()
+///     .paragraph(|p| p.text("Hello!"))
+///     .build_request(TumblrBlogId::BlogName("staff".to_string()));
+/// let created = client.send_request(&request).await?;
+///
+/// let fetched = client
+///     .send_request(&PostGetRequest {
+///         blog_id: TumblrBlogId::BlogName("staff".to_string()),
+///         post_id: created.response.id,
+///     })
+///     .await?;
+/// assert_eq!(fetched.response.parameters.to_plain_text(), "Hello!");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct MockTumblr {
+    address: SocketAddr,
+    state: Arc<Mutex<MockState>>,
+    server: JoinHandle<()>,
+}
+
+impl MockTumblr {
+    /// Starts the server on a free local port.
+    pub async fn start() -> io::Result<Self> {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await?;
+        let address = listener.local_addr()?;
+        let state = Arc::new(Mutex::new(MockState::default()));
+
+        let server = tokio::spawn({
+            let state = state.clone();
+            async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    let state = state.clone();
+                    tokio::spawn(async move {
+                        let mut stream = BufReader::new(stream);
+                        if let Ok(Some(request)) = MockRequest::read(&mut stream).await {
+                            let response = lock(&state).handle(request);
+                            let _ = response.write(&mut stream).await;
+                        }
+                    });
+                }
+            }
+        });
+
+        Ok(Self {
+            address,
+            state,
+            server,
+        })
+    }
+
+    fn state(&self) -> MutexGuard<'_, MockState> {
+        lock(&self.state)
+    }
+
+    pub fn base_url(&self) -> Url {
+        Url::parse(&format!("http://{}/", self.address)).expect("Mock URL failed to be created.")
+    }
+
+    /// Endpoints pointing at this server.
+    pub fn endpoints(&self) -> Endpoints {
+        Endpoints::local(&self.base_url()).expect("Mock endpoints failed to be created.")
+    }
+
+    /// Any credentials work with the mock.
+    pub fn credentials(&self) -> ConsumerCredentials {
+        ConsumerCredentials {
+            consumer_key: "mock-consumer-key".to_string(),
+            consumer_secret: "mock-consumer-secret".to_string(),
+        }
+    }
+
+    /// A client that's already authorized with the mock.
+    ///
+    /// Retries back off for milliseconds rather than seconds, so tests stay fast.
+    pub fn client(&self) -> TumblrClient {
+        let (access_token, refresh_token) = self.state().issue_tokens();
+        TumblrClient::builder(self.credentials())
+            .endpoints(self.endpoints())
+            .retry_policy(RetryPolicy {
+                base_delay: Duration::from_millis(5),
+                max_delay: Duration::from_millis(50),
+                ..Default::default()
+            })
+            .from_tokens(
+                AccessToken::new(access_token),
+                Some(RefreshToken::new(refresh_token)),
+                Utc::now() + chrono::Duration::hours(1),
+            )
+    }
+
+    /// Adds a blog which the mock's user owns.
+    pub fn add_blog(&self, name: &str) -> TumblrUuid {
+        TumblrUuid::new(self.state().add_blog(name))
+    }
+
+    /// Adds a post as if it had been created through the API, from the same fields a create
+    /// request takes. `"state": "submission"` puts it in the blog's inbox, which is how asks,
+    /// which the API can't send, get there.
+    ///
+    /// Returns the post's id, panicking if the blog doesn't exist.
+    pub fn add_post(&self, blog_name: &str, post: Value) -> u64 {
+        self.state()
+            .insert_post(blog_name, &post)
+            .expect("Blog doesn't exist, add it with `add_blog` first.")
+    }
+
+    /// Every post of a blog, newest first, as the API returns them.
+    pub fn posts(&self, blog_name: &str) -> Vec<Value> {
+        self.state()
+            .blogs
+            .iter()
+            .find(|blog| blog.name == blog_name)
+            .map(|blog| blog.posts.clone())
+            .unwrap_or_default()
+    }
+
+    /// The ids of liked posts.
+    pub fn likes(&self) -> Vec<u64> {
+        self.state().likes.clone()
+    }
+
+    /// The names of followed blogs.
+    pub fn following(&self) -> Vec<String> {
+        self.state().following.clone()
+    }
+
+    /// Every request received so far.
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.state().requests.clone()
+    }
+
+    /// Answers the next request with `response`, whatever it is. Queued responses are used in
+    /// order.
+    pub fn respond_next(&self, response: MockResponse) {
+        self.state().queued_responses.push_back(response);
+    }
+
+    /// Rejects the next request with a 429 asking to retry after `retry_after`, without any
+    /// rate limit windows.
+    pub fn rate_limit_next(&self, retry_after: Duration) {
+        self.respond_next(
+            MockResponse::error(StatusCode::TOO_MANY_REQUESTS, 0, "Limit Exceeded")
+                .with_header("Retry-After", retry_after.as_secs()),
+        );
+    }
+
+    /// Rejects the next request with a 429 reporting the hourly window as used up until
+    /// `reset_in`, without a `Retry-After`. This is what
+    /// [`RetryPolicy::wait_for_reset`] waits on.
+    pub fn exhaust_hourly_limit_next(&self, reset_in: Duration) {
+        self.respond_next(
+            MockResponse::error(StatusCode::TOO_MANY_REQUESTS, 0, "Limit Exceeded")
+                .with_header("X-Ratelimit-Perhour-Limit", 1000)
+                .with_header("X-Ratelimit-Perhour-Remaining", 0)
+                .with_header("X-Ratelimit-Perhour-Reset", reset_in.as_secs()),
+        );
+    }
+
+    /// Limits requests per day, after which every request gets a 429. Responses carry
+    /// Tumblr's rate limit headers while a limit is set.
+    pub fn set_daily_limit(&self, limit: Option<u32>) {
+        let mut state = self.state();
+        state.daily_limit = limit;
+        state.daily_remaining = limit;
+    }
+}
+
+impl Drop for MockTumblr {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+/// A panicking test shouldn't take the server down with it.
+fn lock(state: &Mutex<MockState>) -> MutexGuard<'_, MockState> {
+    state
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::{Body, Client, StatusCode};
+    use serde_json::json;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+    };
+    use tokio_util::io::ReaderStream;
+
+    use super::*;
+
+    async fn status(request: reqwest::RequestBuilder) -> StatusCode {
+        request.send().await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn decodes_chunked_bodies() {
+        let mock = MockTumblr::start().await.unwrap();
+        let body = b"split into several chunks".to_vec();
+        let chunks = ReaderStream::with_capacity(std::io::Cursor::new(body.clone()), 4);
+        Client::new()
+            .post(mock.base_url().join("v2/user/like").unwrap())
+            .body(Body::wrap_stream(chunks))
+            .send()
+            .await
+            .unwrap();
+
+        let request = mock.requests().pop().unwrap();
+        assert_eq!(request.header("transfer-encoding"), Some("chunked"));
+        assert_eq!(request.body, body);
+    }
+
+    #[tokio::test]
+    async fn rejects_bodies_that_are_too_large() {
+        let mock = MockTumblr::start().await.unwrap();
+        for headers in [
+            "content-length: 104857600",
+            "transfer-encoding: chunked\r\n\r\n5000000",
+        ] {
+            let mut stream = TcpStream::connect(mock.address).await.unwrap();
+            let request = format!("POST /v2/user/like HTTP/1.1\r\n{headers}\r\n\r\n");
+            stream.write_all(request.as_bytes()).await.unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).await.unwrap();
+            assert!(
+                response.starts_with("HTTP/1.1 413 "),
+                "{headers}: {response}"
+            );
+        }
+        assert!(mock.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn queued_responses_come_first() {
+        let mock = MockTumblr::start().await.unwrap();
+        mock.add_blog("staff");
+        mock.respond_next(MockResponse::ok(json!({ "queued": true })));
+        let request = Client::new()
+            .get(mock.base_url().join("v2/blog/staff/info").unwrap())
+            .query(&[("api_key", "key")]);
+        let response = |request: reqwest::RequestBuilder| async move {
+            let body = request.send().await.unwrap().text().await.unwrap();
+            serde_json::from_str::<Value>(&body).unwrap()["response"].clone()
+        };
+
+        let queued = response(request.try_clone().unwrap()).await;
+        assert_eq!(queued, json!({ "queued": true }));
+        let routed = response(request).await;
+        assert_eq!(routed["blog"]["name"], "staff");
+    }
+
+    #[tokio::test]
+    async fn checks_authorization() {
+        let mock = MockTumblr::start().await.unwrap();
+        mock.add_blog("staff");
+        let token = mock.client().get_access_token().unwrap();
+        let client = Client::new();
+        let url = |path: &str| mock.base_url().join(path).unwrap();
+        let api_key = [("api_key", "key")];
+
+        let public = url("v2/blog/staff/posts");
+        assert_eq!(
+            status(client.get(public.clone())).await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            status(client.get(public).query(&api_key)).await,
+            StatusCode::OK
+        );
+
+        let private = url("v2/user/info");
+        assert_eq!(
+            status(client.get(private.clone()).query(&api_key)).await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            status(client.get(private.clone()).bearer_auth("made-up")).await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            status(client.get(private).bearer_auth(token.secret())).await,
+            StatusCode::OK
+        );
+    }
+}
@@ -0,0 +1,264 @@
+use std::collections::HashMap;
+
+use reqwest::StatusCode;
+use serde_json::Value;
+use tokio::{
+    io::{self, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::TcpStream,
+};
+
+/// Larger request bodies get a 413 rather than being read into memory.
+const MAX_BODY_LENGTH: usize = 64 * 1024 * 1024;
+
+/// A request as received by the mock server.
+#[derive(Debug, Clone)]
+pub struct MockRequest {
+    pub method: String,
+    /// The path without the query string.
+    pub path: String,
+    pub query: HashMap<String, String>,
+    pub headers: HashMap<String, String>,
+    pub body: Vec<u8>,
+}
+
+impl MockRequest {
+    pub(crate) async fn read(stream: &mut BufReader<TcpStream>) -> io::Result<Option<Self>> {
+        let mut request_line = String::new();
+        if stream.read_line(&mut request_line).await? == 0 {
+            return Ok(None);
+        }
+        let mut parts = request_line.split_whitespace();
+        let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+            return Ok(None);
+        };
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+
+        let mut headers = HashMap::new();
+        loop {
+            let mut line = String::new();
+            stream.read_line(&mut line).await?;
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                headers.insert(name.trim().to_ascii_lowercase(), value.trim().to_string());
+            }
+        }
+
+        let chunked = headers
+            .get("transfer-encoding")
+            .is_some_and(|encoding| encoding.to_ascii_lowercase().contains("chunked"));
+        let body = if chunked {
+            read_chunked(stream).await?
+        } else {
+            let length = headers
+                .get("content-length")
+                .and_then(|length| length.parse().ok())
+                .unwrap_or(0);
+            if length <= MAX_BODY_LENGTH {
+                let mut body = vec![0; length];
+                stream.read_exact(&mut body).await?;
+                Some(body)
+            } else {
+                None
+            }
+        };
+        let Some(body) = body else {
+            MockResponse::error(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                0,
+                "Request body is too large.",
+            )
+            .write(stream)
+            .await?;
+            return Ok(None);
+        };
+
+        Ok(Some(Self {
+            method: method.to_string(),
+            path: path.to_string(),
+            query: serde_urlencoded::from_str(query).unwrap_or_default(),
+            headers,
+            body,
+        }))
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .get(&name.to_ascii_lowercase())
+            .map(String::as_str)
+    }
+
+    pub fn bearer_token(&self) -> Option<&str> {
+        self.header("authorization")?.strip_prefix("Bearer ")
+    }
+
+    /// The JSON body, also when sent as the `json` part of a multipart upload.
+    pub fn json(&self) -> Option<Value> {
+        match self.boundary() {
+            Some(_) => serde_json::from_slice(self.part("json")?).ok(),
+            None => serde_json::from_slice(&self.body).ok(),
+        }
+    }
+
+    /// The contents of a `multipart/form-data` body's part named `name`.
+    pub fn part(&self, name: &str) -> Option<&[u8]> {
+        multipart_part(&self.body, self.boundary()?, name)
+    }
+
+    fn boundary(&self) -> Option<&str> {
+        let content_type = self.header("content-type")?;
+        let (_, boundary) = content_type.split_once("boundary=")?;
+        Some(boundary.trim_matches('"'))
+    }
+
+    /// Form fields, from the query string and a `x-www-form-urlencoded` body.
+    pub fn form(&self) -> HashMap<String, String> {
+        let mut form = self.query.clone();
+        if let Ok(body) = serde_urlencoded::from_bytes::<HashMap<String, String>>(&self.body) {
+            form.extend(body);
+        }
+        form
+    }
+}
+
+/// Reads a `transfer-encoding: chunked` body, as sent for streamed uploads. `None` once it
+/// grows past [`MAX_BODY_LENGTH`].
+async fn read_chunked(stream: &mut BufReader<TcpStream>) -> io::Result<Option<Vec<u8>>> {
+    let mut body = Vec::new();
+    loop {
+        let mut size_line = String::new();
+        if stream.read_line(&mut size_line).await? == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        // Chunk extensions after `;` carry nothing we need.
+        let size = size_line.split(';').next().unwrap_or_default().trim();
+        let size = usize::from_str_radix(size, 16)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+
+        if size == 0 {
+            // Skip any trailers up to the blank line ending the body.
+            loop {
+                let mut line = String::new();
+                if stream.read_line(&mut line).await? == 0 || line.trim_end().is_empty() {
+                    return Ok(Some(body));
+                }
+            }
+        }
+
+        let start = body.len();
+        if start.saturating_add(size) > MAX_BODY_LENGTH {
+            return Ok(None);
+        }
+        body.resize(start + size, 0);
+        stream.read_exact(&mut body[start..]).await?;
+        let mut line_end = [0; 2];
+        stream.read_exact(&mut line_end).await?;
+    }
+}
+
+/// The contents of the part named `name`.
+fn multipart_part<'a>(body: &'a [u8], boundary: &str, name: &str) -> Option<&'a [u8]> {
+    let delimiter = format!("--{boundary}");
+    let name = format!("name=\"{name}\"");
+    split(body, delimiter.as_bytes())
+        .into_iter()
+        .find_map(|part| {
+            let header_end = find(part, b"\r\n\r\n")?;
+            let headers = std::str::from_utf8(&part[..header_end]).ok()?;
+            headers.contains(&name).then(|| {
+                let content = &part[header_end + 4..];
+                content.strip_suffix(b"\r\n").unwrap_or(content)
+            })
+        })
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+fn split<'a>(mut haystack: &'a [u8], delimiter: &[u8]) -> Vec<&'a [u8]> {
+    let mut parts = Vec::new();
+    while let Some(index) = find(haystack, delimiter) {
+        parts.push(&haystack[..index]);
+        haystack = &haystack[index + delimiter.len()..];
+    }
+    parts.push(haystack);
+    parts
+}
+
+/// A response from the mock server.
+#[derive(Debug, Clone)]
+pub struct MockResponse {
+    pub status: StatusCode,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl MockResponse {
+    /// Tumblr's usual `meta` and `response` envelope.
+    pub fn ok(response: Value) -> Self {
+        Self::envelope(StatusCode::OK, response, Vec::new())
+    }
+
+    pub fn created(response: Value) -> Self {
+        Self::envelope(StatusCode::CREATED, response, Vec::new())
+    }
+
+    /// An error in Tumblr's format, with one entry in `errors`.
+    pub fn error(status: StatusCode, code: u32, detail: &str) -> Self {
+        let title = status.canonical_reason().unwrap_or_default();
+        Self::envelope(
+            status,
+            Value::Array(Vec::new()),
+            vec![serde_json::json!({ "title": title, "code": code, "detail": detail })],
+        )
+    }
+
+    /// A bare JSON body, without the envelope.
+    pub fn raw_json(status: StatusCode, body: Value) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: body.to_string(),
+        }
+    }
+
+    fn envelope(status: StatusCode, response: Value, errors: Vec<Value>) -> Self {
+        let mut body = serde_json::json!({
+            "meta": {
+                "status": status.as_u16(),
+                "msg": status.canonical_reason().unwrap_or_default(),
+            },
+            "response": response,
+        });
+        if !errors.is_empty() {
+            body["errors"] = Value::Array(errors);
+        }
+        Self::raw_json(status, body)
+    }
+
+    pub fn with_header(mut self, name: impl Into<String>, value: impl ToString) -> Self {
+        self.headers.push((name.into(), value.to_string()));
+        self
+    }
+
+    pub(crate) async fn write(&self, stream: &mut BufReader<TcpStream>) -> io::Result<()> {
+        let mut response = format!(
+            "HTTP/1.1 {} {}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n",
+            self.status.as_u16(),
+            self.status.canonical_reason().unwrap_or_default(),
+            self.body.len(),
+        );
+        for (name, value) in &self.headers {
+            response.push_str(&format!("{name}: {value}\r\n"));
+        }
+        response.push_str("\r\n");
+        response.push_str(&self.body);
+        stream.write_all(response.as_bytes()).await?;
+        stream.flush().await
+    }
+}
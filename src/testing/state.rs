@@ -0,0 +1,504 @@
+use std::collections::{HashSet, VecDeque};
+
+use chrono::{DateTime, Utc};
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+
+use super::http::{MockRequest, MockResponse};
+
+/// Tumblr's code for a request that's missing or has invalid credentials.
+const UNAUTHORIZED_CODE: u32 = 1016;
+const NOT_FOUND_CODE: u32 = 0;
+const RATE_LIMITED_CODE: u32 = 0;
+
+#[derive(Debug)]
+pub(crate) struct MockBlog {
+    pub name: String,
+    pub uuid: String,
+    pub title: String,
+    pub posts: Vec<Value>,
+    pub updated: DateTime<Utc>,
+}
+
+impl MockBlog {
+    fn url(&self) -> String {
+        format!("https://{}.tumblr.com/", self.name)
+    }
+
+    fn matches(&self, blog_id: &str) -> bool {
+        blog_id == self.name
+            || blog_id == self.uuid
+            || blog_id.strip_suffix(".tumblr.com") == Some(&self.name)
+    }
+
+    fn summary(&self) -> Value {
+        json!({
+            "name": self.name,
+            "uuid": self.uuid,
+            "title": self.title,
+            "url": self.url(),
+            "active": true,
+        })
+    }
+
+    fn info(&self) -> Value {
+        let published = self.posts_in_state("published").count();
+        json!({
+            "ask": true,
+            "ask_anon": true,
+            "ask_page_title": "Ask me anything",
+            "asks_allow_media": false,
+            "avatar": [{
+                "width": 64,
+                "height": 64,
+                "url": format!("https://64.media.tumblr.com/avatar_{}_64.pnj", self.name),
+            }],
+            "can_chat": false,
+            "can_subscribe": false,
+            "description": "",
+            "is_nsfw": false,
+            "name": self.name,
+            "posts": published,
+            "share_likes": true,
+            "subscribed": false,
+            "theme": {
+                "header_full_width": 3000,
+                "header_full_height": 1055,
+                "avatar_shape": "square",
+                "background_color": "#FAFAFA",
+                "body_font": "Helvetica Neue",
+                "header_bounds": "",
+                "header_image": "https://assets.tumblr.com/images/default_header/optica_pattern_02.png",
+                "header_image_poster": "https://assets.tumblr.com/images/default_header/optica_pattern_02.png",
+                "header_image_scaled": "https://assets.tumblr.com/images/default_header/optica_pattern_02.png",
+                "header_stretch": true,
+                "link_color": "#529ECC",
+                "show_avatar": true,
+                "show_description": true,
+                "show_header_image": true,
+                "show_title": true,
+                "title_color": "#444444",
+                "title_font": "Gibson",
+                "title_font_weight": "bold",
+            },
+            "title": self.title,
+            "total_posts": published,
+            "updated": self.updated.timestamp(),
+            "url": self.url(),
+            "uuid": self.uuid,
+        })
+    }
+
+    fn posts_in_state<'a>(&'a self, state: &'a str) -> impl Iterator<Item = &'a Value> {
+        self.posts.iter().filter(move |post| post["state"] == state)
+    }
+
+    fn post_mut(&mut self, id: u64) -> Option<&mut Value> {
+        self.posts.iter_mut().find(|post| post["id"] == id)
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct MockState {
+    pub blogs: Vec<MockBlog>,
+    pub requests: Vec<MockRequest>,
+    pub likes: Vec<u64>,
+    pub following: Vec<String>,
+    pub queued_responses: VecDeque<MockResponse>,
+    /// Requests left today, `None` for no limit.
+    pub daily_remaining: Option<u32>,
+    pub daily_limit: Option<u32>,
+    access_tokens: HashSet<String>,
+    refresh_tokens: HashSet<String>,
+    next_id: u64,
+}
+
+impl Default for MockState {
+    fn default() -> Self {
+        Self {
+            blogs: Vec::new(),
+            requests: Vec::new(),
+            likes: Vec::new(),
+            following: Vec::new(),
+            queued_responses: VecDeque::new(),
+            daily_remaining: None,
+            daily_limit: None,
+            access_tokens: HashSet::new(),
+            refresh_tokens: HashSet::new(),
+            next_id: 700_000_000_000,
+        }
+    }
+}
+
+impl MockState {
+    fn next_id(&mut self) -> u64 {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    pub fn add_blog(&mut self, name: &str) -> String {
+        let uuid = format!("t:mock{}", self.next_id());
+        self.blogs.push(MockBlog {
+            name: name.to_string(),
+            uuid: uuid.clone(),
+            title: name.to_string(),
+            posts: Vec::new(),
+            updated: Utc::now(),
+        });
+        uuid
+    }
+
+    /// Issues an access token and a refresh token, as the token endpoint would.
+    pub fn issue_tokens(&mut self) -> (String, String) {
+        let id = self.next_id();
+        let tokens = (format!("mock-access-{id}"), format!("mock-refresh-{id}"));
+        self.access_tokens.insert(tokens.0.clone());
+        self.refresh_tokens.insert(tokens.1.clone());
+        tokens
+    }
+
+    fn blog_mut(&mut self, blog_id: &str) -> Option<&mut MockBlog> {
+        self.blogs.iter_mut().find(|blog| blog.matches(blog_id))
+    }
+
+    fn is_authorized(&self, request: &MockRequest) -> bool {
+        request
+            .bearer_token()
+            .is_some_and(|token| self.access_tokens.contains(token))
+    }
+
+    pub fn handle(&mut self, request: MockRequest) -> MockResponse {
+        self.requests.push(request.clone());
+
+        if let Some(response) = self.queued_responses.pop_front() {
+            return response;
+        }
+
+        if let Some(remaining) = self.daily_remaining {
+            if remaining == 0 {
+                return self.with_rate_limit(MockResponse::error(
+                    StatusCode::TOO_MANY_REQUESTS,
+                    RATE_LIMITED_CODE,
+                    "Daily request limit reached.",
+                ));
+            }
+            self.daily_remaining = Some(remaining - 1);
+        }
+
+        let response = self.route(&request);
+        self.with_rate_limit(response)
+    }
+
+    fn with_rate_limit(&self, response: MockResponse) -> MockResponse {
+        match (self.daily_limit, self.daily_remaining) {
+            (Some(limit), Some(remaining)) => response
+                .with_header("X-Ratelimit-Perday-Limit", limit)
+                .with_header("X-Ratelimit-Perday-Remaining", remaining)
+                .with_header("X-Ratelimit-Perday-Reset", seconds_until_midnight()),
+            _ => response,
+        }
+    }
+
+    fn route(&mut self, request: &MockRequest) -> MockResponse {
+        let method = request.method.as_str();
+        let Some(path) = request.path.strip_prefix("/v2/") else {
+            return not_found();
+        };
+        let segments = path.trim_end_matches('/').split('/').collect::<Vec<_>>();
+
+        if segments == ["oauth2", "token"] && method == "POST" {
+            return self.token(request);
+        }
+
+        // Public reads only need the API key.
+        let public = matches!(
+            (method, segments.as_slice()),
+            ("GET", ["blog", _, "info" | "posts" | "likes"])
+        );
+        let key_only = public && request.query.contains_key("api_key");
+        if !key_only && !self.is_authorized(request) {
+            return unauthorized();
+        }
+
+        match (method, segments.as_slice()) {
+            ("GET", ["blog", blog_id, "info"]) => match self.blog_mut(blog_id) {
+                Some(blog) => MockResponse::ok(json!({ "blog": blog.info() })),
+                None => not_found(),
+            },
+            ("GET", ["blog", blog_id, "posts"]) => self.list_posts(blog_id, "published"),
+            ("GET", ["blog", blog_id, "posts", "queue"]) => self.list_posts(blog_id, "queued"),
+            ("GET", ["blog", blog_id, "posts", "draft"]) => self.list_posts(blog_id, "draft"),
+            ("GET", ["blog", blog_id, "posts", "submission"]) => {
+                self.list_posts(blog_id, "submission")
+            }
+            ("POST", ["blog", blog_id, "posts"]) => self.create_post(blog_id, request),
+            ("GET", ["blog", blog_id, "posts", post_id]) => {
+                match (self.blog_mut(blog_id), post_id.parse()) {
+                    (Some(blog), Ok(post_id)) => match blog.post_mut(post_id) {
+                        Some(post) => MockResponse::ok(post.clone()),
+                        None => not_found(),
+                    },
+                    _ => not_found(),
+                }
+            }
+            ("PUT", ["blog", blog_id, "posts", post_id]) => {
+                self.edit_post(blog_id, post_id, request)
+            }
+            ("POST", ["blog", blog_id, "post", "delete"]) => {
+                let id = request
+                    .form()
+                    .get("id")
+                    .and_then(|id| id.parse::<u64>().ok());
+                match (self.blog_mut(blog_id), id) {
+                    (Some(blog), Some(id)) if blog.posts.iter().any(|post| post["id"] == id) => {
+                        blog.posts.retain(|post| post["id"] != id);
+                        MockResponse::ok(json!({ "id": id.to_string() }))
+                    }
+                    _ => not_found(),
+                }
+            }
+            ("GET", ["blog", blog_id, "likes"]) => {
+                match self.blogs.iter().find(|blog| blog.matches(blog_id)) {
+                    Some(_) => self.liked_posts(),
+                    None => not_found(),
+                }
+            }
+            ("GET", ["user", "info"]) => MockResponse::ok(json!({
+                "user": {
+                    "name": self.blogs.first().map(|blog| blog.name.clone()).unwrap_or_default(),
+                    "likes": self.likes.len(),
+                    "following": self.following.len(),
+                    "default_post_format": "html",
+                    "blogs": self.blogs.iter().map(MockBlog::info).collect::<Vec<_>>(),
+                }
+            })),
+            ("GET", ["user", "likes"]) => self.liked_posts(),
+            ("POST", ["user", action @ ("like" | "unlike")]) => {
+                let Some(id) = request
+                    .form()
+                    .get("id")
+                    .and_then(|id| id.parse::<u64>().ok())
+                else {
+                    return MockResponse::error(StatusCode::BAD_REQUEST, 0, "Missing `id`.");
+                };
+                if !self
+                    .blogs
+                    .iter()
+                    .any(|blog| blog.posts.iter().any(|post| post["id"] == id))
+                {
+                    return not_found();
+                }
+                if *action == "like" && !self.likes.contains(&id) {
+                    self.likes.push(id);
+                } else if *action == "unlike" {
+                    self.likes.retain(|liked| *liked != id);
+                }
+                MockResponse::ok(json!({}))
+            }
+            ("GET", ["user", "following"]) => MockResponse::ok(json!({
+                "total_blogs": self.following.len(),
+                "blogs": self.following.iter().map(|name| json!({
+                    "name": name,
+                    "url": format!("https://{name}.tumblr.com/"),
+                })).collect::<Vec<_>>(),
+            })),
+            ("POST", ["user", action @ ("follow" | "unfollow")]) => {
+                let form = request.form();
+                let Some(name) = form.get("url").map(|url| blog_name_from_url(url)) else {
+                    return MockResponse::error(StatusCode::BAD_REQUEST, 0, "Missing `url`.");
+                };
+                if *action == "follow" {
+                    if !self.following.contains(&name) {
+                        self.following.push(name.clone());
+                    }
+                    MockResponse::ok(json!({ "blog": { "name": name } }))
+                } else {
+                    self.following.retain(|followed| *followed != name);
+                    MockResponse::ok(json!({}))
+                }
+            }
+            _ => not_found(),
+        }
+    }
+
+    fn token(&mut self, request: &MockRequest) -> MockResponse {
+        let form = request.form();
+        let valid = match form.get("grant_type").map(String::as_str) {
+            Some("authorization_code") => form.contains_key("code"),
+            Some("refresh_token") => form
+                .get("refresh_token")
+                .is_some_and(|token| self.refresh_tokens.remove(token)),
+            _ => false,
+        };
+        if !valid {
+            return MockResponse::raw_json(
+                StatusCode::BAD_REQUEST,
+                json!({ "error": "invalid_grant" }),
+            );
+        }
+
+        let (access_token, refresh_token) = self.issue_tokens();
+        MockResponse::raw_json(
+            StatusCode::OK,
+            json!({
+                "access_token": access_token,
+                "token_type": "bearer",
+                "expires_in": 2520,
+                "refresh_token": refresh_token,
+                "scope": "basic write offline_access",
+            }),
+        )
+    }
+
+    fn list_posts(&mut self, blog_id: &str, state: &str) -> MockResponse {
+        let Some(blog) = self.blog_mut(blog_id) else {
+            return not_found();
+        };
+        let posts = blog.posts_in_state(state).cloned().collect::<Vec<_>>();
+        MockResponse::ok(json!({
+            "blog": blog.summary(),
+            "total_posts": posts.len(),
+            "posts": posts,
+        }))
+    }
+
+    fn liked_posts(&self) -> MockResponse {
+        let posts = self
+            .likes
+            .iter()
+            .filter_map(|id| {
+                self.blogs
+                    .iter()
+                    .flat_map(|blog| &blog.posts)
+                    .find(|post| post["id"] == *id)
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        MockResponse::ok(json!({ "liked_count": posts.len(), "liked_posts": posts }))
+    }
+
+    fn create_post(&mut self, blog_id: &str, request: &MockRequest) -> MockResponse {
+        let Some(body) = request.json() else {
+            return MockResponse::error(StatusCode::BAD_REQUEST, 0, "Body isn't valid JSON.");
+        };
+        match self.insert_post(blog_id, &body) {
+            Some(id) => MockResponse::created(json!({ "id": id.to_string() })),
+            None => not_found(),
+        }
+    }
+
+    /// Stores a new post built from a create request's body, `None` if the blog doesn't exist.
+    pub fn insert_post(&mut self, blog_id: &str, body: &Value) -> Option<u64> {
+        let id = self.next_id();
+        let blog = self.blog_mut(blog_id)?;
+
+        let now = Utc::now();
+        let mut post = json!({
+            "object_type": "post",
+            "type": "blocks",
+            "original_type": "regular",
+            "id": id,
+            "id_string": id.to_string(),
+            "blog_name": blog.name,
+            "blog": blog.summary(),
+            "tumblelog_uuid": blog.uuid,
+            "reblog_key": format!("mock{id}"),
+            "post_url": format!("{}post/{id}", blog.url()),
+            "short_url": format!("https://tmblr.co/mock{id}"),
+            "slug": body["slug"].as_str().unwrap_or_default(),
+            "date": now.format("%Y-%m-%d %H:%M:%S GMT").to_string(),
+            "timestamp": now.timestamp(),
+            "summary": "",
+            "note_count": 0,
+            "trail": [],
+            "can_like": true,
+            "can_reblog": true,
+            "can_reply": true,
+            "interactability_reblog": "everyone",
+        });
+        if let Some(parent_id) = body["parent_post_id"].as_u64() {
+            post["parent_post_id"] = json!(parent_id.to_string());
+            post["parent_tumblelog_uuid"] = body["parent_tumblelog_uuid"].clone();
+        }
+        apply_edit(&mut post, body);
+
+        blog.posts.insert(0, post);
+        blog.updated = now;
+        Some(id)
+    }
+
+    fn edit_post(&mut self, blog_id: &str, post_id: &str, request: &MockRequest) -> MockResponse {
+        let Some(body) = request.json() else {
+            return MockResponse::error(StatusCode::BAD_REQUEST, 0, "Body isn't valid JSON.");
+        };
+        let post = post_id
+            .parse()
+            .ok()
+            .and_then(|post_id| self.blog_mut(blog_id)?.post_mut(post_id));
+        match post {
+            Some(post) => {
+                apply_edit(post, &body);
+                MockResponse::ok(json!({ "id": post_id }))
+            }
+            None => not_found(),
+        }
+    }
+}
+
+/// Copies the fields of a create or edit request onto a stored post.
+fn apply_edit(post: &mut Value, body: &Value) {
+    for field in ["content", "layout"] {
+        if let Some(value) = body.get(field) {
+            post[field] = value.clone();
+        } else if post.get(field).is_none() {
+            post[field] = json!([]);
+        }
+    }
+    if let Some(tags) = body["tags"].as_str() {
+        post["tags"] = tags
+            .split(',')
+            .map(str::trim)
+            .filter(|tag| !tag.is_empty())
+            .collect();
+    } else if post.get("tags").is_none() {
+        post["tags"] = json!([]);
+    }
+
+    // Queued posts are listed as "queued", as Tumblr does.
+    let state = match body["state"].as_str() {
+        Some("queue") => "queued".to_string(),
+        Some(state) => state.to_string(),
+        None => post["state"].as_str().unwrap_or("published").to_string(),
+    };
+    post["state"] = json!(state);
+    if state == "queued" {
+        let scheduled = body["publish_on"].is_string();
+        post["queued_state"] = json!(if scheduled { "scheduled" } else { "queued" });
+        post["publish_on"] = body["publish_on"].clone();
+    }
+}
+
+fn blog_name_from_url(url: &str) -> String {
+    let host = url
+        .trim_start_matches("https://")
+        .trim_start_matches("http://")
+        .trim_end_matches('/');
+    host.strip_suffix(".tumblr.com").unwrap_or(host).to_string()
+}
+
+fn seconds_until_midnight() -> i64 {
+    let now = Utc::now();
+    let midnight = (now.date_naive() + chrono::Days::new(1))
+        .and_hms_opt(0, 0, 0)
+        .expect("Midnight is a valid time.")
+        .and_utc();
+    (midnight - now).num_seconds()
+}
+
+fn unauthorized() -> MockResponse {
+    MockResponse::error(StatusCode::UNAUTHORIZED, UNAUTHORIZED_CODE, "Unauthorized")
+}
+
+fn not_found() -> MockResponse {
+    MockResponse::error(StatusCode::NOT_FOUND, NOT_FOUND_CODE, "Not Found")
+}